@@ -1,15 +1,49 @@
-use crate::cli::Args;
-use crate::cmd::{ensure_in_path, run_cmd};
-use crate::fs_utils::{create_dir_all, find_first_with_ext, whisper_models_dir};
-use crate::hf::fetch_hf_files_cached;
-use crate::models::{build_basename_from_wav, pick_model_interactive, resolve_or_download_model};
+use crate::backend::{
+    BackendKind, BackendSettings, DetectedLanguage, OutOfMemory, TranscribeOptions, Transcriber,
+    check_capabilities, create_backend, temp_dir_placeholder,
+};
+use crate::cli::{CacheAction, CatalogArgs, Command, ConfigAction, ModelsArgs, TranscribeArgs};
+use crate::config;
+use crate::constants::CACHE_TTL;
+use crate::error::Vid2txtError;
+use crate::filter::{self, FilterOptions};
+use crate::fs_utils::{
+    create_dir_all, create_parent_dir, find_first_with_ext, move_file, same_file,
+    whisper_models_dir,
+};
+use crate::glossary::Glossary;
+use crate::hf::{
+    CatalogOptions, HfFile, HfTokens, ModelRepo, cache_dir_path, fetch_hf_files_cached,
+    local_model_files,
+};
+use crate::interrupt::{self, Partial};
+use crate::language;
+use crate::logging::{self, Level};
+use crate::media::{
+    MediaInfo, is_whisper_wav, probe_local_duration, probe_local_tags, probe_remote_duration,
+    read_info_json, wav_duration,
+};
+use crate::models::{
+    build_basename_from_wav, download_model_if_missing, english_variant, format_size, match_model,
+    pick_model_interactive, resolve_local_model, resolve_or_download_model,
+};
+use crate::naming::{NameFields, OutputTemplate, unresolved_info, with_ext};
+use crate::progress::{Progress, run_with_progress};
+use crate::recommend::{
+    FallbackDirection, Hardware, estimated_memory, fallback_model, is_english, recommend,
+};
+use crate::report::{self, ErrorReport, InputReport, say, warning};
+use crate::tools::{Tool, Tools, command_line};
+use crate::transcript::{Attempt, Transcript};
 use anyhow::{Context, Result, anyhow};
-use clap::Parser;
-use indicatif::{ProgressBar, ProgressStyle};
+use clap::ValueEnum;
 use regex::Regex;
+use sanitize_filename::sanitize;
 use std::fs;
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 use std::process::Command as PCommand;
+use std::time::{Duration, Instant};
 use tempfile::tempdir;
 
 /// Return true if `s` looks like a *remote* URL we should hand to yt-dlp.
@@ -97,226 +131,1462 @@ fn try_infer_with_exts(candidate: PathBuf) -> Option<PathBuf> {
     None
 }
 
-pub fn run() -> Result<()> {
-    let args = Args::parse();
+/// Resolve a local input path (relative to cwd), inferring a missing media extension.
+fn resolve_local_input(input: &str) -> Result<PathBuf> {
+    // Resolve relative/absolute (don’t require existence yet)
+    let candidate = {
+        let p = PathBuf::from(input);
+        if p.is_absolute() {
+            p
+        } else {
+            std::env::current_dir()
+                .context("Failed to resolve current working directory")?
+                .join(p)
+        }
+    };
 
-    let out_dir = args
-        .out
-        .clone()
-        .unwrap_or_else(|| std::env::current_dir().expect("cwd"));
-    let verbose = args.verbose;
+    // If missing extension / not found, try common media extensions
+    let input_path = if candidate.exists() {
+        candidate
+    } else if let Some(found) = try_infer_with_exts(candidate.clone()) {
+        found
+    } else {
+        // Last attempt: normalize just for a nicer error message
+        let display_cand = candidate.canonicalize().unwrap_or(candidate.clone());
+        return Err(Vid2txtError::InvalidInput(format!(
+            "Input file not found. Tried: {}\nHint: include the extension or use one of: .mp4 .mkv .webm .mov .m4a .mp3 .wav .flac .avi .m4v .aac .opus",
+            display_cand.display()
+        ))
+        .into());
+    };
 
-    // whisper-cli always needed; ffmpeg always needed; yt-dlp only for remote URLs.
-    ensure_in_path("ffmpeg")?;
-    ensure_in_path("whisper-cli")?;
+    if !input_path.is_file() {
+        let display_path = input_path
+            .canonicalize()
+            .unwrap_or_else(|_| input_path.clone());
+        return Err(Vid2txtError::InvalidInput(format!(
+            "Input is not a file: {}",
+            display_path.display()
+        ))
+        .into());
+    }
+    Ok(input_path)
+}
 
-    // Determine models dir next to whisper-cli binary
-    let models_dir = whisper_models_dir()?;
-    create_dir_all(&models_dir)?;
+fn catalog_options(args: &CatalogArgs, ttl: Duration) -> Result<CatalogOptions> {
+    let repos = if args.hf_repos.is_empty() {
+        vec![ModelRepo::parse(
+            crate::constants::HF_DEFAULT_REPO,
+            &args.hf_endpoint,
+        )?]
+    } else {
+        args.hf_repos
+            .iter()
+            .map(|r| ModelRepo::parse(r, &args.hf_endpoint))
+            .collect::<Result<_>>()?
+    };
+    Ok(CatalogOptions {
+        repos,
+        tokens: HfTokens::parse(&args.hf_token)?,
+        cache_dir: cache_dir_path()?,
+        refresh: args.refresh_models,
+        offline: args.offline,
+        prefer_quantized: args.prefer_quantized,
+        ttl,
+    })
+}
 
-    // Cache-aware fetch of HF file list (order already honors preference)
-    let files = fetch_hf_files_cached(args.refresh_models, args.prefer_quantized)?;
+/// Fetch the model catalog. When it can't be loaded (no network and no cached list,
+/// or `--offline` without a cache), fall back to the models already in `models_dir`.
+fn load_catalog(opts: &CatalogOptions, models_dir: &Path) -> Result<Vec<HfFile>> {
+    match fetch_hf_files_cached(opts) {
+        Err(e) => {
+            let local = local_model_files(models_dir, &opts.repos, opts.prefer_quantized);
+            if local.is_empty() {
+                return Err(e);
+            }
+            warning!("⚠️  {e:#}; using local models only");
+            Ok(local)
+        }
+        r => r,
+    }
+}
 
-    // --list-models mode
-    if args.list_models {
-        if files.is_empty() {
-            return Err(anyhow!("No models found in Hugging Face API response"));
+/// Decide model path: local file/path first; the catalog is only fetched when
+/// resolving an alias, recommending (`--model auto`) or showing the interactive picker.
+/// With `--dry-run` only the cached catalog is read and nothing is downloaded.
+fn select_model(
+    args: &TranscribeArgs,
+    tools: &Tools,
+    catalog: &CatalogOptions,
+    models_dir: &Path,
+    input: &str,
+    local_input: Option<&Path>,
+) -> Result<PathBuf> {
+    let verbose = args.verbose > 0;
+    let dry_run = args.dry_run;
+    let fetch = |file: &HfFile| {
+        if dry_run {
+            let dest = file.local_path(models_dir);
+            if !dest.exists() {
+                say!(
+                    "⬇️  Would download model: {} ({})",
+                    file.rfilename,
+                    file.source.id
+                );
+            }
+            Ok(dest)
+        } else {
+            let token = catalog.tokens.for_repo(&file.source);
+            download_model_if_missing(file, models_dir, token, catalog.offline, verbose)
         }
-        println!("Available models ({}):", files.len());
-        for f in &files {
-            let size = f
-                .size
-                .map(|s| crate::models::format_size(s))
-                .unwrap_or_default();
-            if size.is_empty() {
-                println!("- {}", f.rfilename);
-            } else {
-                println!("- {} ({size})", f.rfilename);
+    };
+
+    // Never block on the picker without a terminal (cron, CI), with --yes or --dry-run
+    let interactive = is_interactive() && !dry_run;
+    let requested = match &args.model {
+        Some(m) => Some(m.clone()),
+        None if args.yes || !interactive => {
+            let m = args
+                .default_model
+                .clone()
+                .unwrap_or_else(|| "auto".to_string());
+            if !interactive && !dry_run {
+                warning!("ℹ️  No terminal for the model picker; using model '{m}'");
             }
+            Some(m)
+        }
+        None => None,
+    };
+
+    let auto = requested
+        .as_deref()
+        .is_some_and(|m| m.eq_ignore_ascii_case("auto"));
+    if !auto
+        && let Some(p) = requested
+            .as_deref()
+            .and_then(|m| resolve_local_model(m, models_dir))
+    {
+        return Ok(p);
+    }
+
+    // Cache-aware fetch of HF file list (order already honors preference)
+    let mut files = if dry_run {
+        let cached_only = CatalogOptions {
+            offline: true,
+            ..catalog.clone()
+        };
+        fetch_hf_files_cached(&cached_only).unwrap_or_else(|_| {
+            say!("ℹ️  No cached model list; --dry-run only considers downloaded models");
+            local_model_files(models_dir, &catalog.repos, catalog.prefer_quantized)
+        })
+    } else {
+        load_catalog(catalog, models_dir)?
+    };
+    if let Some(m) = requested.as_deref().filter(|_| !auto) {
+        if dry_run {
+            return fetch(match_model(
+                m,
+                models_dir,
+                &files,
+                catalog.prefer_quantized,
+                catalog.offline,
+            )?);
         }
-        return Ok(());
+        return resolve_or_download_model(
+            m,
+            models_dir,
+            &files,
+            catalog.prefer_quantized,
+            &catalog.tokens,
+            catalog.offline,
+            verbose,
+        );
     }
 
-    // Decide model path: provided alias/path or interactive picker
-    let model_path = if let Some(m) = args.model.clone() {
-        resolve_or_download_model(&m, &models_dir, &files, args.prefer_quantized, verbose)?
+    if catalog.offline {
+        files.retain(|f| f.local_path(models_dir).exists());
+    }
+    let duration = match local_input {
+        Some(p) => probe_local_duration(tools, p),
+        None if !catalog.offline && !dry_run => probe_remote_duration(tools, input),
+        None => None,
+    };
+    let rec = recommend(
+        &files,
+        &Hardware::detect(),
+        duration,
+        is_english(&args.language),
+        catalog.prefer_quantized,
+    );
+
+    let picked = if auto {
+        let rec = rec.ok_or_else(|| {
+            Vid2txtError::ModelResolution(if args.model.is_some() {
+                "--model auto: no usable model in the catalog".into()
+            } else {
+                "No model to use without the interactive picker: pass --model <name> or set VID2TXT_DEFAULT_MODEL".into()
+            })
+        })?;
+        let f = files[rec.index].clone();
+        say!("🤖 Auto-selected model: {} ({})", f.rfilename, rec.reason);
+        f
     } else {
-        let picked = pick_model_interactive(&files, args.prefer_quantized, &models_dir)?;
-        resolve_or_download_model(&picked, &models_dir, &files, args.prefer_quantized, verbose)?
+        if let Some(r) = &rec {
+            say!("★ Recommended: {} ({})", files[r.index].rfilename, r.reason);
+        }
+        pick_model_interactive(
+            &files,
+            catalog.prefer_quantized,
+            models_dir,
+            rec.map(|r| r.index),
+        )?
     };
+    fetch(&picked)
+}
 
-    // Create output directory if missing
-    create_dir_all(&out_dir)?;
+/// The picker needs a terminal on both ends (dialoguer draws on stderr).
+fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
 
-    let input = args
-        .url
-        .clone()
-        .ok_or_else(|| anyhow!("No input provided. Pass a URL or a local video path."))?;
+/// Warn when the model likely needs more RAM than is currently free.
+fn warn_if_low_memory(model_path: &Path) {
+    let Ok(meta) = fs::metadata(model_path) else {
+        return;
+    };
+    let hw = Hardware::detect();
+    let needed = estimated_memory(meta.len());
+    if hw.available_memory > 0 && needed > hw.available_memory {
+        warning!(
+            "⚠️  Model {} needs ~{} RAM but only {} is free; expect swapping or an out-of-memory failure",
+            model_path.display(),
+            format_size(needed),
+            format_size(hw.available_memory)
+        );
+    }
+}
 
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::with_template("{spinner} {msg}")
-            .unwrap()
-            .tick_chars("⠇⠋⠙⠸⠴⠦⠇"),
-    );
+/// A command-line input: remote URLs go through yt-dlp, local files through ffmpeg.
+enum Input {
+    Remote(String),
+    Local(PathBuf),
+}
 
-    // We'll set these based on the branch (URL vs local)
-    let final_wav: PathBuf;
-    let base_name: String;
-
-    if is_probable_url(&input) {
-        // Remote URL → use yt-dlp
-        ensure_in_path("yt-dlp")?;
-
-        pb.set_message("Downloading & extracting audio (yt-dlp)…");
-
-        // Temporary working directory for yt-dlp
-        let temp = tempdir()?;
-        let temp_path = temp.path();
-
-        // yt-dlp → WAV (highest quality)
-        let output_tpl = temp_path.join("%(title)s.%(ext)s");
-        let status = run_cmd(
-            PCommand::new("yt-dlp")
-                .arg(&input)
-                .arg("-f")
-                .arg("bestaudio/best")
-                .arg("--extract-audio")
-                .arg("--audio-format")
-                .arg("wav")
-                .arg("--audio-quality")
-                .arg("0")
-                .arg("--restrict-filenames")
-                .arg("--windows-filenames")
-                .arg("-o")
-                .arg(output_tpl.display().to_string()),
-            verbose,
-        )?;
-        if !status.success() {
-            pb.finish_and_clear();
-            return Err(anyhow!("yt-dlp failed"));
+impl Input {
+    fn display(&self) -> String {
+        match self {
+            Input::Remote(u) => u.clone(),
+            Input::Local(p) => p.display().to_string(),
         }
+    }
+}
+
+pub fn run() -> Result<()> {
+    let (cli, resolved) = config::load_args()?;
+    interrupt::install()?;
+    let cache_ttl = cli.cache_ttl.unwrap_or(CACHE_TTL);
 
-        // Find the produced WAV file
-        let wav_path = find_first_with_ext(temp_path, "wav")?
-            .ok_or_else(|| anyhow!("No WAV file produced by yt-dlp"))?;
+    match &cli.command {
+        Command::Transcribe(args) => transcribe(args, cache_ttl, Tools::resolve(&cli.tools)?),
+        Command::Models(args) => list_models(args, cache_ttl, &Tools::resolve(&cli.tools)?),
+        Command::Cache { action } => match action {
+            CacheAction::Status => crate::cache::status(cache_ttl),
+            CacheAction::Clear => crate::cache::clear(),
+        },
+        Command::Doctor(args) => {
+            crate::doctor::run(args.json, &Tools::resolve(&cli.tools)?, &resolved)
+        }
+        Command::Config {
+            action: ConfigAction::Show,
+        } => config::show(&resolved),
+    }
+}
 
-        // Build a nice base name and move WAV to destination
-        base_name = build_basename_from_wav(&wav_path);
-        final_wav = out_dir.join(format!("{base_name}.wav"));
+/// `vid2txt models`
+fn list_models(args: &ModelsArgs, cache_ttl: Duration, tools: &Tools) -> Result<()> {
+    let catalog = catalog_options(&args.catalog, cache_ttl)?;
+    let models_dir = whisper_models_dir(tools)?;
+    let mut files = load_catalog(&catalog, &models_dir)?;
+    if args.downloaded {
+        files.retain(|f| f.local_path(&models_dir).exists());
+        if files.is_empty() {
+            println!("No models downloaded to {}", models_dir.display());
+            return Ok(());
+        }
+    }
+    if files.is_empty() {
+        return Err(anyhow!("No models found in Hugging Face API response"));
+    }
+    println!("Available models ({}):", files.len());
+    for f in &files {
+        let size = f.size.map(format_size).unwrap_or_default();
+        let mark = if f.local_path(&models_dir).exists() {
+            " ✓"
+        } else {
+            ""
+        };
+        if size.is_empty() {
+            println!("- {}{mark} [{}]", f.rfilename, f.source.id);
+        } else {
+            println!("- {} ({size}){mark} [{}]", f.rfilename, f.source.id);
+        }
+    }
+    Ok(())
+}
 
-        fs::rename(&wav_path, &final_wav)
-            .or_else(|_| fs::copy(&wav_path, &final_wav).and_then(|_| fs::remove_file(&wav_path)))
-            .with_context(|| format!("Failed to move WAV to {}", final_wav.display()))?;
+/// `vid2txt transcribe` (also what runs without a subcommand)
+fn transcribe(args: &TranscribeArgs, cache_ttl: Duration, tools: Tools) -> Result<()> {
+    report::set_mode(if args.json_events {
+        report::Mode::Events
+    } else if args.json {
+        report::Mode::Json
     } else {
-        // Local file → use ffmpeg directly
-        pb.set_message("Extracting audio from local file (ffmpeg)…");
-
-        // Resolve relative/absolute (don’t require existence yet)
-        let candidate = {
-            let p = PathBuf::from(&input);
-            if p.is_absolute() {
-                p
-            } else {
-                std::env::current_dir()
-                    .context("Failed to resolve current working directory")?
-                    .join(p)
+        report::Mode::Human
+    });
+    logging::set_level(match args.verbose {
+        _ if args.quiet => Level::Quiet,
+        0 => Level::Normal,
+        1 => Level::Verbose,
+        _ => Level::Debug,
+    });
+    if let Some(path) = &args.log_file {
+        logging::open(path)?;
+    }
+    let Batch {
+        mut backend,
+        opts,
+        pipeline,
+        inputs,
+    } = match prepare(args, cache_ttl, tools) {
+        Ok(batch) => batch,
+        Err(e) => {
+            report_setup_failure(args, &e);
+            return Err(e);
+        }
+    };
+    if args.dry_run {
+        return print_plan(&inputs, backend.as_ref(), &opts, &pipeline);
+    }
+
+    // One backend instance for the whole batch (e.g. a server keeps its model loaded)
+    let mut failed = 0usize;
+    let mut first_error = None;
+    // (input, transcript if it succeeded), for the Ctrl-C summary
+    let mut finished: Vec<(String, Option<PathBuf>)> = Vec::new();
+    for (i, input) in inputs.iter().enumerate() {
+        let log = if inputs.len() > 1 {
+            say!("▶ {}", input.display());
+            logging::start_job(i + 1, &input.display())?
+        } else {
+            None
+        };
+        report::input_started(&input.display());
+        let started = Instant::now();
+        let mut rep = InputReport {
+            input: input.display(),
+            backend: backend.name().to_string(),
+            log,
+            ..Default::default()
+        };
+        let mut result = process_input(input, backend.as_mut(), &opts, &pipeline, &mut rep);
+        // whatever a stopped tool reported, the cause was Ctrl-C
+        if result.is_err() && interrupt::interrupted() {
+            result = Err(Vid2txtError::Cancelled.into());
+        }
+        rep.timings.total = started.elapsed().as_secs_f64();
+        if let Err(e) = &result {
+            rep.status = report::Status::Failed;
+            rep.error = Some(ErrorReport::new(e));
+        }
+        report::input_finished(&rep);
+
+        if interrupt::interrupted() {
+            if result.is_ok() {
+                finished.push((input.display(), rep.transcript.clone()));
             }
+            logging::end_job();
+            print_cancelled(&inputs, i, &finished);
+            return Err(Vid2txtError::Cancelled.into());
+        }
+        finished.push((input.display(), rep.transcript.clone()));
+        if let Err(e) = result {
+            if inputs.len() == 1 {
+                return Err(e);
+            }
+            warning!("❌ {}: {e:#}", input.display());
+            failed += 1;
+            first_error.get_or_insert(e);
+        }
+        logging::end_job();
+    }
+
+    // the first failure's cause decides the exit code
+    if let Some(e) = first_error {
+        return Err(e.context(format!("{failed} of {} inputs failed", inputs.len())));
+    }
+    Ok(())
+}
+
+/// Report every input as failed when the batch could not start, so `--json` still
+/// gets a result per input.
+fn report_setup_failure(args: &TranscribeArgs, e: &anyhow::Error) {
+    let backend = args
+        .backend
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default();
+    for input in &args.inputs {
+        report::input_finished(&InputReport {
+            input: input.clone(),
+            status: report::Status::Failed,
+            backend: backend.clone(),
+            error: Some(ErrorReport::new(e)),
+            ..Default::default()
+        });
+    }
+}
+
+/// Everything a batch needs before its first input.
+struct Batch {
+    backend: Box<dyn Transcriber>,
+    /// With the selected model
+    opts: TranscribeOptions,
+    pipeline: Pipeline,
+    inputs: Vec<Input>,
+}
+
+fn prepare(args: &TranscribeArgs, cache_ttl: Duration, tools: Tools) -> Result<Batch> {
+    let out_dir = args
+        .out
+        .clone()
+        .unwrap_or_else(|| std::env::current_dir().expect("cwd"));
+    let verbose = args.verbose > 0;
+
+    // Reject flags the backend can't honour before downloading anything
+    let backend = create_backend(
+        args.backend,
+        BackendSettings {
+            server_url: args.server_url.clone(),
+            api_base_url: args.api_base_url.clone(),
+            api_key: args.api_key.clone(),
+            api_model: args.api_model.clone(),
+            api_response_format: args.api_response_format,
+            tools: tools.clone(),
+        },
+    )?;
+    let glossary = args.glossary.as_deref().map(Glossary::load).transpose()?;
+    let output_template = OutputTemplate::parse(&args.output_template)?;
+    let filter = if args.no_filter {
+        None
+    } else {
+        Some(FilterOptions::new(
+            args.max_repeats as usize,
+            args.drop_no_speech,
+            args.hallucination_phrases.as_deref(),
+        )?)
+    };
+    let mut opts = TranscribeOptions {
+        model: PathBuf::new(),
+        language: args.language.clone(),
+        threads: args.threads,
+        prompt: initial_prompt(args, glossary.as_ref())?,
+        translate: args.translate,
+        word_timestamps: args.word_timestamps,
+        diarize: args.diarize,
+        decoding: args.decoding(),
+        verbose,
+    };
+    check_capabilities(backend.as_ref(), &opts)?;
+    if !backend.capabilities().decoding {
+        let ignored = opts.decoding.unsupported_by_api();
+        if !ignored.is_empty() {
+            warning!(
+                "⚠️  The {} backend only supports --temperature; ignoring {}",
+                backend.name(),
+                ignored.join(", ")
+            );
+        }
+    }
+
+    // The backend's own tools; yt-dlp only for remote URLs.
+    for tool in backend.required_tools() {
+        tools.require(tool)?;
+    }
+
+    let catalog = catalog_options(&args.catalog, cache_ttl)?;
+    if catalog.offline {
+        let remote = match args.backend {
+            BackendKind::Openai => Some("--backend openai"),
+            BackendKind::WhisperServer if args.server_url.is_some() => Some("--server-url"),
+            _ => None,
         };
+        if let Some(flag) = remote {
+            return Err(Vid2txtError::Network(format!(
+                "--offline: {flag} sends the audio over the network. Use a local backend instead."
+            ))
+            .into());
+        }
+    }
 
-        // If missing extension / not found, try common media extensions
-        let input_path = if candidate.exists() {
-            candidate
-        } else if let Some(found) = try_infer_with_exts(candidate.clone()) {
-            found
+    if args.inputs.is_empty() {
+        return Err(Vid2txtError::InvalidInput(
+            "No input provided. Pass a URL or a local video path.".into(),
+        )
+        .into());
+    }
+    // Resolve local inputs up front so a typo fails before any model download
+    let mut inputs = Vec::with_capacity(args.inputs.len());
+    for raw in &args.inputs {
+        if is_probable_url(raw) {
+            if catalog.offline {
+                return Err(Vid2txtError::Network(format!(
+                    "--offline: cannot download remote input {raw}. Pass a local file instead."
+                ))
+                .into());
+            }
+            inputs.push(Input::Remote(raw.clone()));
         } else {
-            // Last attempt: normalize just for a nicer error message
-            let display_cand = candidate.canonicalize().unwrap_or(candidate.clone());
-            pb.finish_and_clear();
-            return Err(anyhow!(
-                "Input file not found. Tried: {}\nHint: include the extension or use one of: .mp4 .mkv .webm .mov .m4a .mp3 .wav .flac .avi .m4v .aac .opus",
-                display_cand.display()
-            ));
+            inputs.push(Input::Local(resolve_local_input(raw)?));
+        }
+    }
+    // ffmpeg extracts the audio, unless every input already is a WAV whisper can read
+    if inputs.iter().any(|i| needs_extraction(i, opts.diarize)) {
+        tools.require(Tool::Ffmpeg)?;
+    }
+
+    if backend.uses_local_model() {
+        // Determine models dir next to the whisper.cpp binaries
+        let models_dir = whisper_models_dir(&tools)?;
+        if !args.dry_run {
+            create_dir_all(&models_dir)?;
+        }
+
+        // The first input stands in for the batch when recommending a model
+        let (first, first_local) = match &inputs[0] {
+            Input::Remote(u) => (u.as_str(), None),
+            Input::Local(p) => ("", Some(p.as_path())),
         };
+        opts.model = select_model(args, &tools, &catalog, &models_dir, first, first_local)?;
+        warn_if_low_memory(&opts.model);
+    }
 
-        // Canonicalize (best-effort) for cleaner messages
-        let display_path = input_path
-            .canonicalize()
-            .unwrap_or_else(|_| input_path.clone());
+    // Create output directory if missing
+    if !args.dry_run {
+        create_dir_all(&out_dir)?;
+    }
+
+    let fallback = if args.no_model_fallback || !backend.uses_local_model() {
+        None
+    } else {
+        Some(ModelFallback {
+            catalog,
+            models_dir: whisper_models_dir(&tools)?,
+            min_confidence: args.min_confidence,
+            max_attempts: args.max_attempts as usize,
+        })
+    };
+    let pipeline = Pipeline {
+        out_dir,
+        language: LanguagePolicy {
+            detect_seconds: args.detect_seconds,
+            allowed: args.allowed_languages.clone(),
+            english_model: args.english_model,
+        },
+        filter,
+        glossary,
+        fallback,
+        output_template,
+        tools,
+    };
+    Ok(Batch {
+        backend,
+        opts,
+        pipeline,
+        inputs,
+    })
+}
 
-        if !input_path.is_file() {
-            pb.finish_and_clear();
-            return Err(anyhow!("Input is not a file: {}", display_path.display()));
+/// After Ctrl-C during `inputs[current]`: what was done, what wasn't.
+fn print_cancelled(inputs: &[Input], current: usize, finished: &[(String, Option<PathBuf>)]) {
+    warning!("⏹️  Cancelled");
+    for (input, transcript) in finished {
+        match transcript {
+            Some(t) => warning!("   ✅ {input} → {}", t.display()),
+            None => warning!("   ❌ {input} (failed)"),
         }
+    }
+    for (i, input) in inputs.iter().enumerate().skip(finished.len()) {
+        if i == current {
+            warning!("   ⏹️  {} (interrupted)", input.display());
+        } else {
+            warning!("   ⏭️  {} (not started)", input.display());
+        }
+    }
+}
 
-        // Base name from the input file
-        base_name = input_path
-            .file_stem()
-            .map(|s| s.to_string_lossy().to_string())
-            .filter(|s| !s.is_empty())
-            .unwrap_or_else(|| "audio".to_string());
-
-        final_wav = out_dir.join(format!("{base_name}.wav"));
-
-        // ffmpeg: extract PCM WAV (mono, 16 kHz — great default for STT)
-        let status = run_cmd(
-            PCommand::new("ffmpeg")
-                .arg("-y") // overwrite if exists
-                .arg("-i")
-                .arg(&input_path)
-                .arg("-vn")
-                .arg("-acodec")
-                .arg("pcm_s16le")
-                .arg("-ar")
-                .arg("16000")
-                .arg("-ac")
-                .arg("1")
-                .arg(&final_wav),
+/// Handle one line of `ffmpeg -progress pipe:1` output. The total comes from ffprobe,
+/// or from the "Duration:" line ffmpeg prints itself when ffprobe is missing.
+fn parse_ffmpeg_progress(line: &str, duration: &mut Option<f64>, progress: &Progress) -> bool {
+    let line = line.trim();
+    if duration.is_none()
+        && let Some(rest) = line.strip_prefix("Duration: ")
+    {
+        *duration = rest.split(',').next().and_then(parse_hms);
+        return false;
+    }
+    if let Some(us) = line.strip_prefix("out_time_us=") {
+        if let (Some(total), Ok(us)) = (*duration, us.parse::<f64>()) {
+            progress.set_fraction(us / 1_000_000.0 / total);
+        }
+        return true;
+    }
+    if line == "progress=end" {
+        progress.set_fraction(1.0);
+    }
+    // the remaining key=value lines of the progress protocol
+    line.split_once('=')
+        .is_some_and(|(k, _)| !k.is_empty() && !k.contains(' '))
+}
+
+/// `HH:MM:SS.xx` → seconds
+fn parse_hms(s: &str) -> Option<f64> {
+    let mut secs = 0.0;
+    for part in s.trim().split(':') {
+        secs = secs * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(secs)
+}
+
+/// Handle one line of `yt-dlp --newline` output, e.g.
+/// `[download]  42.3% of ~ 3.20MiB at 1.05MiB/s ETA 00:02`.
+fn parse_ytdlp_progress(line: &str, progress: &Progress) -> bool {
+    if let Some(rest) = line.strip_prefix("[download]")
+        && let Some(pct) = rest.trim_start().split('%').next()
+        && let Ok(pct) = pct.parse::<f64>()
+    {
+        progress.set_fraction(pct / 100.0);
+        return true;
+    }
+    if line.starts_with("[ExtractAudio]") {
+        progress.set_message("Converting audio to WAV (yt-dlp)…");
+    }
+    false
+}
+
+/// Settings shared by every input of a batch.
+struct Pipeline {
+    out_dir: PathBuf,
+    language: LanguagePolicy,
+    filter: Option<FilterOptions>,
+    glossary: Option<Glossary>,
+    /// `None` with --no-model-fallback or a backend without local models
+    fallback: Option<ModelFallback>,
+    output_template: OutputTemplate,
+    tools: Tools,
+}
+
+/// Retry transcription with another catalog model after an out-of-memory failure
+/// (smaller) or a low-confidence result (larger).
+struct ModelFallback {
+    catalog: CatalogOptions,
+    models_dir: PathBuf,
+    /// Retry with a larger model when the mean token probability is below this
+    min_confidence: Option<f32>,
+    max_attempts: usize,
+}
+
+impl ModelFallback {
+    /// Find and fetch the next model, or `None` when the catalog has nothing suitable.
+    fn next_model(
+        &self,
+        current: &str,
+        direction: FallbackDirection,
+        language: &str,
+        tried: &[String],
+        verbose: bool,
+    ) -> Result<Option<PathBuf>> {
+        let files = load_catalog(&self.catalog, &self.models_dir)?;
+        let Some(i) = fallback_model(&files, current, direction, is_english(language), tried)
+        else {
+            return Ok(None);
+        };
+        let path = download_model_if_missing(
+            &files[i],
+            &self.models_dir,
+            self.catalog.tokens.for_repo(&files[i].source),
+            self.catalog.offline,
             verbose,
         )?;
-        if !status.success() {
-            pb.finish_and_clear();
-            return Err(anyhow!(
-                "ffmpeg failed to extract audio from {}",
-                display_path.display()
-            ));
+        Ok(Some(path))
+    }
+}
+
+/// Run the backend, switching models per [`ModelFallback`] until a result is accepted
+/// or the attempts run out. Every attempt is recorded when more than one was made.
+fn transcribe_with_fallback(
+    wav: &Path,
+    backend: &mut dyn Transcriber,
+    opts: &mut TranscribeOptions,
+    pipeline: &Pipeline,
+) -> Result<Transcript> {
+    let mut attempts: Vec<Attempt> = Vec::new();
+    loop {
+        let progress = Progress::stage(
+            "transcribe",
+            format!("Transcribing with {}…", backend.name()),
+        );
+        let result = backend.transcribe(wav, opts, &progress);
+        progress.finish();
+        // a tool stopped by Ctrl-C is no reason to try another model
+        interrupt::check()?;
+
+        let Some(fallback) = &pipeline.fallback else {
+            return result;
+        };
+        let model = opts
+            .model
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let confidence = result.as_ref().ok().and_then(|t| t.confidence);
+        let retry = match &result {
+            Err(e) if e.downcast_ref::<OutOfMemory>().is_some() => {
+                Some((FallbackDirection::Smaller, "out of memory".to_string()))
+            }
+            Ok(_) => match (fallback.min_confidence, confidence) {
+                (Some(min), Some(c)) if c < min => Some((
+                    FallbackDirection::Larger,
+                    format!("low confidence {:.0}%", c * 100.0),
+                )),
+                _ => None,
+            },
+            Err(_) => None,
+        };
+
+        let next = match &retry {
+            Some((direction, _)) if attempts.len() + 1 < fallback.max_attempts => {
+                let mut tried: Vec<String> = attempts.iter().map(|a| a.model.clone()).collect();
+                tried.push(model.clone());
+                fallback.next_model(&model, *direction, &opts.language, &tried, opts.verbose)?
+            }
+            _ => None,
+        };
+        attempts.push(Attempt {
+            model: model.clone(),
+            outcome: match (&retry, &result) {
+                (Some((_, why)), _) => why.clone(),
+                (None, Ok(_)) => "ok".into(),
+                (None, Err(e)) => format!("failed: {e}"),
+            },
+            confidence,
+        });
+
+        let Some(next) = next else {
+            if attempts.len() > 1 && result.is_err() {
+                let tried: Vec<&str> = attempts.iter().map(|a| a.model.as_str()).collect();
+                return result
+                    .with_context(|| format!("No model worked (tried {})", tried.join(", ")));
+            }
+            let mut transcript = result?;
+            if attempts.len() > 1 {
+                transcript.attempts = attempts;
+            }
+            return Ok(transcript);
+        };
+        say!(
+            "↩️  {model}: {}; retrying with {}",
+            attempts.last().map(|a| a.outcome.as_str()).unwrap_or(""),
+            next.file_name().unwrap_or_default().to_string_lossy()
+        );
+        warn_if_low_memory(&next);
+        opts.model = next;
+    }
+}
+
+/// What to do with the spoken language of each input.
+struct LanguagePolicy {
+    /// Length of the detection pass with `--language auto`
+    detect_seconds: u32,
+    /// `--allowed-languages` codes; empty allows everything
+    allowed: Vec<String>,
+    /// Switch to the `.en` model variant for English audio
+    english_model: bool,
+}
+
+impl LanguagePolicy {
+    fn check(&self, language: &str) -> Result<()> {
+        let code = language::to_code(language).unwrap_or(language);
+        if self.allowed.is_empty() || self.allowed.iter().any(|a| a == code) {
+            Ok(())
+        } else {
+            Err(Vid2txtError::InvalidInput(format!(
+                "Detected language '{code}' is not in --allowed-languages ({})",
+                self.allowed.join(", ")
+            ))
+            .into())
         }
     }
+}
+
+/// With `--language auto`, run the backend's detection pass, enforce the policy and
+/// pin `opts.language` (and possibly the `.en` model) for the transcription.
+fn detect_language(
+    wav: &Path,
+    backend: &mut dyn Transcriber,
+    opts: &mut TranscribeOptions,
+    policy: &LanguagePolicy,
+) -> Result<Option<DetectedLanguage>> {
+    if opts.language != "auto" {
+        return Ok(None);
+    }
+    let progress = Progress::stage("detect_language", "Detecting language…");
+    let result = backend.detect_language(wav, opts, policy.detect_seconds, &progress);
+    progress.finish();
+    let Some(detected) = result? else {
+        return Ok(None);
+    };
+
+    match detected.probability {
+        Some(p) => say!(
+            "🌐 Detected language: {} ({:.0}%)",
+            detected.code,
+            p * 100.0
+        ),
+        None => say!("🌐 Detected language: {}", detected.code),
+    }
+    policy.check(&detected.code)?;
+    opts.language = detected.code.clone();
+
+    if policy.english_model
+        && detected.code == "en"
+        && backend.uses_local_model()
+        && let Some(en) = english_variant(&opts.model)
+    {
+        say!(
+            "Switching to English-only model {}",
+            en.file_name().unwrap_or_default().to_string_lossy()
+        );
+        opts.model = en;
+    }
+    Ok(Some(detected))
+}
+
+/// `--prompt`/`--prompt-file`, followed by as many glossary terms as whisper accepts.
+fn initial_prompt(args: &TranscribeArgs, glossary: Option<&Glossary>) -> Result<Option<String>> {
+    let prompt = match (&args.prompt, &args.prompt_file) {
+        (Some(p), _) => Some(p.clone()),
+        (None, Some(path)) => Some(
+            fs::read_to_string(path)
+                .with_context(|| format!("Failed to read prompt file {}", path.display()))?
+                .trim()
+                .to_string(),
+        ),
+        (None, None) => None,
+    };
+    let Some(glossary) = glossary.filter(|g| !g.is_empty()) else {
+        return Ok(prompt.filter(|p| !p.is_empty()));
+    };
+    let (packed, dropped) = glossary.pack_prompt(prompt.as_deref());
+    if dropped > 0 {
+        warning!(
+            "⚠️  {dropped} glossary term(s) don't fit in whisper's prompt; they are only used to correct the transcript"
+        );
+    }
+    Ok(Some(packed).filter(|p| !p.is_empty()))
+}
 
-    pb.set_message("Transcribing with whisper-cli…");
+/// `--dry-run`: what [`process_input`] would do for each input, without running it.
+/// Names that depend on the download or the detected language stay `{placeholders}`.
+fn print_plan(
+    inputs: &[Input],
+    backend: &dyn Transcriber,
+    opts: &TranscribeOptions,
+    pipeline: &Pipeline,
+) -> Result<()> {
+    say!("📋 Dry run: nothing is downloaded, transcribed or written");
+    say!("Backend: {}", backend.name());
+    if backend.uses_local_model() {
+        say!("Model: {}", opts.model.display());
+    }
+    let detect_seconds = (opts.language == "auto").then_some(pipeline.language.detect_seconds);
+    let mut naming = opts.clone();
+    if detect_seconds.is_some() {
+        naming.language = "{lang}".into();
+    }
+
+    for input in inputs {
+        let mut commands = Vec::new();
+        let wav = match input {
+            Input::Local(path) => {
+                let extract = needs_extraction(input, opts.diarize);
+                say!(
+                    "\n▶ {} (local file → {})",
+                    path.display(),
+                    if extract { "ffmpeg" } else { "copy" }
+                );
+                let stem = path
+                    .file_stem()
+                    .map(|s| sanitize(s.to_string_lossy()))
+                    .filter(|s| !s.is_empty())
+                    .unwrap_or_else(|| "audio".to_string());
+                let info = if pipeline.output_template.uses_metadata() {
+                    probe_local_tags(&pipeline.tools, path)
+                } else {
+                    MediaInfo::default()
+                };
+                let wav = with_ext(
+                    &output_base(pipeline, backend, &info, &stem, &naming),
+                    "wav",
+                );
+                if extract {
+                    commands.push(command_line(&extract_command(
+                        &pipeline.tools,
+                        path,
+                        &with_ext(&wav, "part"),
+                        opts.diarize,
+                    )?));
+                }
+                wav
+            }
+            Input::Remote(url) => {
+                say!("\n▶ {url} (URL → yt-dlp)");
+                let wav = with_ext(
+                    &output_base(pipeline, backend, &unresolved_info(), "{stem}", &naming),
+                    "wav",
+                );
+                commands.push(command_line(&download_command(
+                    &pipeline.tools,
+                    url,
+                    &temp_dir_placeholder(),
+                )?));
+                wav
+            }
+        };
+        commands.extend(backend.plan(&wav, opts, detect_seconds)?);
+        for (i, c) in commands.iter().enumerate() {
+            say!("  {}. {c}", i + 1);
+        }
+        let base = wav.with_extension("");
+        say!("  Transcript: {}", with_ext(&base, "txt").display());
+        if opts.word_timestamps {
+            say!("  Word timings: {}", with_ext(&base, "json").display());
+        }
+        say!("  WAV: {}", wav.display());
+    }
+    Ok(())
+}
 
-    // whisper-cli flags: -m <model> -f <wav> -otxt -of <output_base> -l <lang> [-t <threads>]
-    let output_base = PathBuf::from(&out_dir).join(&base_name);
+/// ffmpeg: extract PCM WAV (mono, 16 kHz — great default for STT), reporting its
+/// position on stdout (-progress) for the bar. `--diarize` keeps two channels, since
+/// whisper-cli tells speakers apart by channel.
+fn extract_command(tools: &Tools, input: &Path, wav: &Path, stereo: bool) -> Result<PCommand> {
+    let mut cmd = tools.command(Tool::Ffmpeg)?;
+    cmd.arg("-y") // overwrite if exists
+        .arg("-nostats")
+        .arg("-progress")
+        .arg("pipe:1")
+        .arg("-i")
+        .arg(input)
+        .arg("-vn")
+        .arg("-acodec")
+        .arg("pcm_s16le")
+        .arg("-ar")
+        .arg("16000")
+        .arg("-ac")
+        .arg(if stereo { "2" } else { "1" })
+        // the name may not end in .wav (see process_input)
+        .args(["-f", "wav"])
+        .arg(wav);
+    Ok(cmd)
+}
 
-    let mut whisper = PCommand::new("whisper-cli");
-    whisper.arg("-m").arg(&model_path);
-    whisper.arg("-f").arg(&final_wav);
-    whisper.arg("-otxt");
-    whisper.arg("-of").arg(&output_base);
-    whisper.arg("-l").arg(&args.language);
-    if let Some(t) = args.threads {
-        whisper.arg("-t").arg(t.to_string());
+/// Whether `input` needs ffmpeg: anything but a local 16 kHz PCM WAV with the channels
+/// [`extract_command`] would produce. yt-dlp converts downloads with ffmpeg, too.
+fn needs_extraction(input: &Input, diarize: bool) -> bool {
+    match input {
+        Input::Local(path) => !is_whisper_wav(path, if diarize { 2 } else { 1 }),
+        Input::Remote(_) => true,
     }
+}
 
-    let status = run_cmd(&mut whisper, verbose)?;
-    if !status.success() {
-        pb.finish_and_clear();
-        return Err(anyhow!("whisper-cli failed"));
+/// A WAV named like a local `.wav` input in the same directory would replace it.
+fn refuse_overwriting_input(input: &Input, wav: &Path) -> Result<()> {
+    if let Input::Local(path) = input
+        && same_file(path, wav)
+    {
+        return Err(Vid2txtError::InvalidInput(format!(
+            "The extracted audio would overwrite the input {}; choose another --out or --output-template",
+            wav.display()
+        ))
+        .into());
     }
+    Ok(())
+}
 
-    pb.finish_and_clear();
+/// yt-dlp → WAV (highest quality) in `temp_dir`; --newline prints one progress line
+/// per update. The info JSON next to it feeds --output-template.
+fn download_command(tools: &Tools, url: &str, temp_dir: &Path) -> Result<PCommand> {
+    let output_tpl = temp_dir.join("%(title)s.%(ext)s");
+    let mut cmd = tools.command(Tool::YtDlp)?;
+    cmd.arg(url)
+        .arg("--newline")
+        .arg("-f")
+        .arg("bestaudio/best")
+        .arg("--extract-audio")
+        .arg("--audio-format")
+        .arg("wav")
+        .arg("--audio-quality")
+        .arg("0")
+        .arg("--restrict-filenames")
+        .arg("--windows-filenames")
+        .arg("--write-info-json")
+        .arg("-o")
+        .arg(output_tpl.display().to_string());
+    Ok(cmd)
+}
 
-    let transcript_txt = out_dir.join(format!("{base_name}.txt"));
-    if transcript_txt.exists() {
-        println!("✅ Done! Transcript: {}", transcript_txt.display());
-        println!("Model used: {}", model_path.display());
-        println!("WAV saved at: {}", final_wav.display());
+/// Where an input's outputs go (without extension), per `--output-template`.
+fn output_base(
+    pipeline: &Pipeline,
+    backend: &dyn Transcriber,
+    info: &MediaInfo,
+    stem: &str,
+    opts: &TranscribeOptions,
+) -> PathBuf {
+    let model = if backend.uses_local_model() {
+        opts.model
+            .file_stem()
+            .map(|s| s.to_string_lossy())
+            .map(|s| s.strip_prefix("ggml-").unwrap_or(&s).to_string())
+            .unwrap_or_default()
     } else {
-        println!(
-            "⚠️ whisper-cli ran, but no .txt was found at {}",
+        backend.name().to_string()
+    };
+    let fields = NameFields {
+        info,
+        stem,
+        model: &model,
+        lang: Some(opts.language.as_str()).filter(|l| *l != "auto"),
+    };
+    pipeline
+        .out_dir
+        .join(pipeline.output_template.render(&fields))
+}
+
+/// Acquire audio for one input, transcribe it and write the transcript files,
+/// recording what it got to in `report`.
+fn process_input(
+    input: &Input,
+    backend: &mut dyn Transcriber,
+    opts: &TranscribeOptions,
+    pipeline: &Pipeline,
+    report: &mut InputReport,
+) -> Result<()> {
+    let verbose = opts.verbose;
+    let started = Instant::now();
+
+    // We'll set these based on the branch (URL vs local)
+    let mut final_wav: PathBuf;
+    let stem: String;
+    let info: MediaInfo;
+    let template = &pipeline.output_template;
+
+    match input {
+        Input::Local(input_path) => {
+            // Local file → use ffmpeg directly
+            let progress = Progress::stage("extract", "Extracting audio from local file (ffmpeg)…");
+
+            // Canonicalize (best-effort) for cleaner messages
+            let display_path = input_path
+                .canonicalize()
+                .unwrap_or_else(|_| input_path.clone());
+
+            // Base name from the input file
+            stem = input_path
+                .file_stem()
+                .map(|s| sanitize(s.to_string_lossy()))
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "audio".to_string());
+            info = if template.uses_metadata() {
+                probe_local_tags(&pipeline.tools, input_path)
+            } else {
+                MediaInfo::default()
+            };
+
+            final_wav = with_ext(&output_base(pipeline, backend, &info, &stem, opts), "wav");
+            refuse_overwriting_input(input, &final_wav)?;
+            create_parent_dir(&final_wav)?;
+            // ffmpeg writes next to the WAV; only a complete one takes its name
+            let part = with_ext(&final_wav, "part");
+            let partial = Partial::new(&part);
+
+            if needs_extraction(input, opts.diarize) {
+                let mut duration = probe_local_duration(&pipeline.tools, input_path);
+                let run = run_with_progress(
+                    &mut extract_command(&pipeline.tools, input_path, &part, opts.diarize)?,
+                    verbose,
+                    &progress,
+                    |line| parse_ffmpeg_progress(line, &mut duration, &progress),
+                )?;
+                progress.finish();
+                if !run.status.success() {
+                    return Err(Vid2txtError::Extraction {
+                        message: format!(
+                            "ffmpeg failed to extract audio from {}",
+                            display_path.display()
+                        ),
+                        stderr: run.stderr_tail,
+                    }
+                    .into());
+                }
+            } else {
+                progress.set_message("Copying WAV input…");
+                fs::copy(input_path, &part).with_context(|| {
+                    format!(
+                        "Failed to copy {} to {}",
+                        input_path.display(),
+                        part.display()
+                    )
+                })?;
+                progress.finish();
+            }
+            fs::rename(&part, &final_wav).with_context(|| {
+                format!(
+                    "Failed to move {} to {}",
+                    part.display(),
+                    final_wav.display()
+                )
+            })?;
+            partial.done();
+        }
+        Input::Remote(url) => {
+            // Remote URL → use yt-dlp
+            let progress = Progress::stage("download", "Downloading audio (yt-dlp)…");
+
+            // Temporary working directory for yt-dlp
+            let temp = tempdir()?;
+            let temp_path = temp.path();
+            let _scratch = Partial::scratch(temp_path);
+
+            let run = run_with_progress(
+                &mut download_command(&pipeline.tools, url, temp_path)?,
+                verbose,
+                &progress,
+                |line| parse_ytdlp_progress(line, &progress),
+            )?;
+            progress.finish();
+            if !run.status.success() {
+                return Err(Vid2txtError::Download {
+                    message: format!("yt-dlp failed to download {url}"),
+                    stderr: run.stderr_tail,
+                }
+                .into());
+            }
+
+            // Find the produced WAV file
+            let wav_path =
+                find_first_with_ext(temp_path, "wav")?.ok_or_else(|| Vid2txtError::Download {
+                    message: "No WAV file produced by yt-dlp".into(),
+                    stderr: run.stderr_tail,
+                })?;
+
+            // Build a nice base name and move WAV to destination
+            stem = build_basename_from_wav(&wav_path);
+            info = find_first_with_ext(temp_path, "json")?
+                .map(|p| read_info_json(&p))
+                .unwrap_or_default();
+            final_wav = with_ext(&output_base(pipeline, backend, &info, &stem, opts), "wav");
+            move_file(&wav_path, &final_wav)?;
+        }
+    }
+
+    interrupt::check()?;
+    report.timings.acquire = Some(started.elapsed().as_secs_f64());
+    report.audio_seconds = wav_duration(&final_wav);
+    report.wav = Some(final_wav.clone());
+
+    let mut opts = opts.clone();
+    let started = Instant::now();
+    let detected = detect_language(&final_wav, backend, &mut opts, &pipeline.language);
+    report.timings.detect_language = detected
+        .as_ref()
+        .is_ok_and(Option::is_some)
+        .then(|| started.elapsed().as_secs_f64());
+    let detected = detected?;
+    interrupt::check()?;
+    if let Some(d) = &detected {
+        report.language = Some(d.code.clone());
+        report.language_probability = d.probability;
+    }
+
+    let started = Instant::now();
+    let transcript = transcribe_with_fallback(&final_wav, backend, &mut opts, pipeline);
+    report.timings.transcribe = Some(started.elapsed().as_secs_f64());
+    if backend.uses_local_model() {
+        report.model = opts
+            .model
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string());
+    }
+    let mut transcript = transcript?;
+    match detected {
+        Some(d) => {
+            transcript.language = Some(d.code);
+            transcript.language_probability = d.probability;
+        }
+        // No detection pass: check what the backend reported instead
+        None => {
+            if let Some(lang) = &transcript.language {
+                pipeline.language.check(lang)?;
+            }
+        }
+    }
+    let removed = pipeline
+        .filter
+        .as_ref()
+        .map(|f| filter::clean(&mut transcript, f))
+        .unwrap_or_default();
+    if let Some(g) = &pipeline.glossary {
+        g.correct(&mut transcript);
+    }
+
+    report.language = transcript.language.clone();
+    report.segments = Some(transcript.segments.len());
+    report.filtered = Some(removed.len());
+    report.attempts = transcript.attempts.clone();
+
+    // {model} and {lang} are final now; the WAV follows the transcript's name
+    if let Some(lang) = &transcript.language {
+        opts.language = lang.clone();
+    }
+    let base = output_base(pipeline, backend, &info, &stem, &opts);
+    if template.uses_outcome() && with_ext(&base, "wav") != final_wav {
+        let wav = with_ext(&base, "wav");
+        refuse_overwriting_input(input, &wav)?;
+        move_file(&final_wav, &wav)?;
+        // drop a subdirectory named after the provisional values, if now empty
+        if let Some(dir) = final_wav.parent()
+            && dir != pipeline.out_dir
+        {
+            let _ = fs::remove_dir(dir);
+        }
+        final_wav = wav;
+        report.wav = Some(final_wav.clone());
+    }
+
+    let transcript_txt = with_ext(&base, "txt");
+    transcript.write_txt(&transcript_txt)?;
+    report.transcript = Some(transcript_txt.clone());
+    if transcript.is_empty() {
+        say!(
+            "⚠️ {} ran, but recognized no speech; wrote an empty {}",
+            backend.name(),
             transcript_txt.display()
         );
+    } else {
+        say!("✅ Done! Transcript: {}", transcript_txt.display());
+    }
+    if opts.word_timestamps {
+        let transcript_json = with_ext(&base, "json");
+        transcript.write_json(&transcript_json)?;
+        say!("Word timings: {}", transcript_json.display());
+        report.transcript_json = Some(transcript_json);
+    }
+    if backend.uses_local_model() {
+        say!("Model used: {}", opts.model.display());
+    }
+    say!("WAV saved at: {}", final_wav.display());
+    if !transcript.attempts.is_empty() {
+        let chain: Vec<String> = transcript
+            .attempts
+            .iter()
+            .map(|a| format!("{} ({})", a.model, a.outcome))
+            .collect();
+        say!("Attempts: {}", chain.join(" → "));
+    }
+    if !removed.is_empty() {
+        say!("🧹 Filtered {} span(s):", removed.len());
+        for r in &removed {
+            say!("   {} {}: {}", filter::format_span(r), r.reason, r.text);
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allowed: &[&str]) -> LanguagePolicy {
+        LanguagePolicy {
+            detect_seconds: 30,
+            allowed: allowed.iter().map(|a| a.to_string()).collect(),
+            english_model: false,
+        }
+    }
+
+    #[test]
+    fn allowed_languages_limit_detection() {
+        assert!(policy(&[]).check("ja").is_ok());
+        let en_de = policy(&["en", "de"]);
+        assert!(en_de.check("de").is_ok());
+        assert!(en_de.check("German").is_ok());
+        let err = en_de.check("fr").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Vid2txtError>(),
+            Some(Vid2txtError::InvalidInput(_))
+        ));
+        assert!(err.to_string().contains("'fr'"));
+        // codes vid2txt does not know are compared as reported
+        assert!(en_de.check("xx").is_err());
+    }
+
+    #[test]
+    fn setup_failures_report_every_input() {
+        use crate::cli::{Cli, Command};
+        use clap::Parser;
+        let cli = Cli::try_parse_from([
+            "vid2txt",
+            "transcribe",
+            "a.mp4",
+            "b.mp4",
+            "--backend",
+            "openai",
+        ])
+        .unwrap();
+        let Command::Transcribe(args) = cli.command else {
+            panic!("expected transcribe");
+        };
+        let e = anyhow::Error::from(Vid2txtError::Network("--offline: --backend openai".into()));
+        let lines = report::capture::lines(report::Mode::Events, || {
+            report_setup_failure(&args, &e);
+        });
+        assert_eq!(lines.len(), 2);
+        for (line, input) in lines.iter().zip(["a.mp4", "b.mp4"]) {
+            assert_eq!(
+                *line,
+                serde_json::json!({
+                    "event": "input_finished",
+                    "input": input,
+                    "status": "failed",
+                    "backend": "openai",
+                    "timings": { "total": 0.0 },
+                    "error": { "kind": "network", "message": "--offline: --backend openai" }
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn parses_ffmpeg_progress() {
+        // ffprobe missing: the total comes from ffmpeg's own banner
+        let progress = Progress::stage("extract", "test");
+        let mut duration = None;
+        for (line, consumed, percent) in [
+            (
+                "  Duration: 00:01:40.00, start: 0.000000, bitrate: 128 kb/s",
+                false,
+                None,
+            ),
+            (
+                "  Stream #0:0: Audio: aac (LC), 44100 Hz, stereo, fltp",
+                false,
+                None,
+            ),
+            ("frame=0", true, None),
+            ("out_time_us=25000000", true, Some(25)),
+            ("out_time_ms=25000000", true, Some(25)),
+            ("out_time=00:00:25.000000", true, Some(25)),
+            ("speed=50.1x", true, Some(25)),
+            ("progress=continue", true, Some(25)),
+            ("out_time_us=99000000", true, Some(99)),
+            ("size=N/A", true, Some(99)),
+            ("progress=end", true, Some(100)),
+            ("[aac @ 0x55d0c8] Too large remapped id", false, Some(100)),
+        ] {
+            assert_eq!(
+                parse_ffmpeg_progress(line, &mut duration, &progress),
+                consumed,
+                "{line}"
+            );
+            assert_eq!(progress.percent(), percent, "{line}");
+        }
+        assert_eq!(duration, Some(100.0));
+    }
+
+    #[test]
+    fn ffmpeg_progress_needs_a_duration() {
+        let progress = Progress::stage("extract", "test");
+        let mut duration = None;
+        assert!(parse_ffmpeg_progress(
+            "out_time_us=25000000",
+            &mut duration,
+            &progress
+        ));
+        assert_eq!(progress.percent(), None);
+        // a known total (ffprobe) is not replaced by the banner
+        let mut duration = Some(50.0);
+        parse_ffmpeg_progress(
+            "  Duration: 00:01:40.00, start: 0.0",
+            &mut duration,
+            &progress,
+        );
+        assert_eq!(duration, Some(50.0));
+        parse_ffmpeg_progress("out_time_us=25000000", &mut duration, &progress);
+        assert_eq!(progress.percent(), Some(50));
+    }
+
+    #[test]
+    fn parses_hms() {
+        assert_eq!(parse_hms("00:01:40.00"), Some(100.0));
+        assert_eq!(parse_hms("01:00:00.50"), Some(3600.5));
+        assert_eq!(parse_hms(" 02:03 "), Some(123.0));
+        assert_eq!(parse_hms("N/A"), None);
+        assert_eq!(parse_hms(""), None);
+    }
+
+    #[test]
+    fn parses_ytdlp_progress() {
+        let progress = Progress::stage("download", "test");
+        for (line, consumed, percent) in [
+            ("[youtube] dQw4w9WgXcQ: Downloading webpage", false, None),
+            (
+                "[info] dQw4w9WgXcQ: Downloading 1 format(s): 251",
+                false,
+                None,
+            ),
+            ("[download] Destination: /tmp/x/audio.webm", false, None),
+            (
+                "[download]   0.0% of ~   3.20MiB at  Unknown B/s ETA Unknown",
+                true,
+                Some(0),
+            ),
+            (
+                "[download]  42.3% of ~   3.20MiB at    1.05MiB/s ETA 00:02",
+                true,
+                Some(42),
+            ),
+            (
+                "[download]  61.0% of Unknown total size at 1.00MiB/s ETA Unknown",
+                true,
+                Some(61),
+            ),
+            (
+                "[download] 100% of    3.20MiB in 00:00:03 at 1.02MiB/s",
+                true,
+                Some(100),
+            ),
+            (
+                "[ExtractAudio] Destination: /tmp/x/audio.wav",
+                false,
+                Some(100),
+            ),
+            ("Deleting original file /tmp/x/audio.webm", false, Some(100)),
+        ] {
+            assert_eq!(parse_ytdlp_progress(line, &progress), consumed, "{line}");
+            assert_eq!(progress.percent(), percent, "{line}");
+        }
+    }
+}
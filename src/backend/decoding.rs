@@ -0,0 +1,173 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// Named starting points for [`DecodingParams`]; explicit flags override them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DecodingPreset {
+    /// Greedy decoding without temperature fallback
+    Fast,
+    /// whisper.cpp's defaults: beam search of 5 with fallback
+    Balanced,
+    /// Wider beam and more candidates when sampling; slowest
+    Accurate,
+}
+
+impl DecodingPreset {
+    pub fn params(self) -> DecodingParams {
+        match self {
+            DecodingPreset::Fast => DecodingParams {
+                beam_size: Some(1),
+                best_of: Some(1),
+                no_fallback: true,
+                ..Default::default()
+            },
+            DecodingPreset::Balanced => DecodingParams {
+                beam_size: Some(5),
+                best_of: Some(5),
+                temperature: Some(0.0),
+                temperature_inc: Some(0.2),
+                ..Default::default()
+            },
+            DecodingPreset::Accurate => DecodingParams {
+                beam_size: Some(8),
+                best_of: Some(8),
+                temperature: Some(0.0),
+                temperature_inc: Some(0.2),
+                entropy_threshold: Some(2.4),
+                logprob_threshold: Some(-1.0),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// whisper.cpp decoding knobs. `None`/`false` leaves the engine's default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodingParams {
+    /// Beam width; 1 means greedy decoding
+    pub beam_size: Option<u32>,
+    /// Candidates kept when sampling with temperature > 0
+    pub best_of: Option<u32>,
+    pub temperature: Option<f32>,
+    /// Step added to the temperature when a segment fails the thresholds
+    pub temperature_inc: Option<f32>,
+    /// Never retry failed segments at a higher temperature
+    pub no_fallback: bool,
+    pub entropy_threshold: Option<f32>,
+    pub logprob_threshold: Option<f32>,
+    pub no_speech_threshold: Option<f32>,
+    /// Maximum segment length in characters
+    pub max_len: Option<u32>,
+    /// Split segments on word rather than token boundaries (with `max_len`)
+    pub split_on_word: bool,
+    /// Maximum number of text context tokens carried between windows
+    pub max_context: Option<u32>,
+    /// Number of processors (whisper-cli/whisper-server `-p`)
+    pub processors: Option<u32>,
+}
+
+impl DecodingParams {
+    /// The temperature step actually used; `no_fallback` forces 0.
+    pub fn effective_temperature_inc(&self) -> Option<f32> {
+        if self.no_fallback {
+            Some(0.0)
+        } else {
+            self.temperature_inc
+        }
+    }
+
+    /// Command-line flags understood by both whisper-cli and whisper-server.
+    pub fn whisper_cpp_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut push = |flag: &str, value: Option<String>| {
+            if let Some(v) = value {
+                args.push(flag.to_string());
+                args.push(v);
+            }
+        };
+        push("-bs", self.beam_size.map(|v| v.to_string()));
+        push("-bo", self.best_of.map(|v| v.to_string()));
+        push("-tp", self.temperature.map(|v| v.to_string()));
+        push("-tpi", self.temperature_inc.map(|v| v.to_string()));
+        push("-et", self.entropy_threshold.map(|v| v.to_string()));
+        push("-lpt", self.logprob_threshold.map(|v| v.to_string()));
+        push("-nth", self.no_speech_threshold.map(|v| v.to_string()));
+        push("-ml", self.max_len.map(|v| v.to_string()));
+        push("-mc", self.max_context.map(|v| v.to_string()));
+        push("-p", self.processors.map(|v| v.to_string()));
+        if self.no_fallback {
+            args.push("-nf".into());
+        }
+        if self.split_on_word {
+            args.push("-sow".into());
+        }
+        args
+    }
+
+    /// Set options an OpenAI-compatible API can't take (it only accepts `temperature`).
+    pub fn unsupported_by_api(&self) -> Vec<&'static str> {
+        [
+            (self.beam_size.is_some(), "--beam-size"),
+            (self.best_of.is_some(), "--best-of"),
+            (self.temperature_inc.is_some(), "--temperature-inc"),
+            (self.no_fallback, "--no-fallback"),
+            (self.entropy_threshold.is_some(), "--entropy-threshold"),
+            (self.logprob_threshold.is_some(), "--logprob-threshold"),
+            (self.no_speech_threshold.is_some(), "--no-speech-threshold"),
+            (self.max_len.is_some(), "--max-len"),
+            (self.split_on_word, "--split-on-word"),
+            (self.max_context.is_some(), "--max-context"),
+            (self.processors.is_some(), "--processors"),
+        ]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_map_to_whisper_cpp_flags() {
+        for (preset, args) in [
+            (DecodingPreset::Fast, "-bs 1 -bo 1 -nf"),
+            (DecodingPreset::Balanced, "-bs 5 -bo 5 -tp 0 -tpi 0.2"),
+            (
+                DecodingPreset::Accurate,
+                "-bs 8 -bo 8 -tp 0 -tpi 0.2 -et 2.4 -lpt -1",
+            ),
+        ] {
+            assert_eq!(
+                preset.params().whisper_cpp_args().join(" "),
+                args,
+                "{preset:?}"
+            );
+        }
+        assert!(DecodingParams::default().whisper_cpp_args().is_empty());
+    }
+
+    #[test]
+    fn no_fallback_zeroes_the_temperature_step() {
+        let fast = DecodingPreset::Fast.params();
+        assert_eq!(fast.effective_temperature_inc(), Some(0.0));
+        let balanced = DecodingPreset::Balanced.params();
+        assert_eq!(balanced.effective_temperature_inc(), Some(0.2));
+        assert_eq!(DecodingParams::default().effective_temperature_inc(), None);
+    }
+
+    #[test]
+    fn lists_what_an_api_cannot_take() {
+        assert_eq!(
+            DecodingPreset::Fast.params().unsupported_by_api(),
+            ["--beam-size", "--best-of", "--no-fallback"]
+        );
+        let temperature_only = DecodingParams {
+            temperature: Some(0.4),
+            ..Default::default()
+        };
+        assert!(temperature_only.unsupported_by_api().is_empty());
+    }
+}
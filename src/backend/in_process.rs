@@ -0,0 +1,307 @@
+use super::{Capabilities, DetectedLanguage, TranscribeOptions, Transcriber};
+use crate::error::Vid2txtError;
+use crate::interrupt::Partial;
+use crate::progress::{Progress, run_with_progress};
+use crate::tools::{Tool, Tools};
+use crate::transcript::{Segment, Transcript, Word};
+use anyhow::{Context, Result, anyhow};
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+/// Runs whisper.cpp inside the vid2txt process through `whisper-rs` (CPU build).
+/// The model stays loaded for the whole batch.
+pub struct InProcessBackend {
+    loaded: Option<(PathBuf, WhisperContext)>,
+    tools: Tools,
+}
+
+impl InProcessBackend {
+    pub fn new(tools: Tools) -> Self {
+        Self {
+            loaded: None,
+            tools,
+        }
+    }
+
+    fn context(&mut self, model: &Path) -> Result<&WhisperContext> {
+        if self.loaded.as_ref().is_none_or(|(p, _)| p != model) {
+            let ctx = WhisperContext::new_with_params(model, WhisperContextParameters::default())
+                .map_err(|e| anyhow!("Failed to load model {}: {e}", model.display()))?;
+            self.loaded = Some((model.to_path_buf(), ctx));
+        }
+        Ok(&self.loaded.as_ref().expect("model loaded").1)
+    }
+}
+
+impl Transcriber for InProcessBackend {
+    fn name(&self) -> &'static str {
+        "in-process"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            translate: true,
+            word_timestamps: true,
+            diarization: false,
+            decoding: true,
+        }
+    }
+
+    fn detect_language(
+        &mut self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        seconds: u32,
+        progress: &Progress,
+    ) -> Result<Option<DetectedLanguage>> {
+        let audio = read_pcm_16k_mono(&self.tools, wav, opts.verbose, progress)?;
+        let head = &audio[..audio.len().min(seconds as usize * 16000)];
+        let threads = opts.threads.unwrap_or(4).max(1) as usize;
+        let mut state = self
+            .context(&opts.model)?
+            .create_state()
+            .map_err(|e| anyhow!("Failed to create whisper state: {e}"))?;
+        state
+            .pcm_to_mel(head, threads)
+            .map_err(|e| anyhow!("Failed to compute the spectrogram: {e}"))?;
+        let (id, probs) =
+            state
+                .lang_detect(0, threads)
+                .map_err(|e| Vid2txtError::Transcription {
+                    message: format!("whisper language detection failed: {e}"),
+                    stderr: Vec::new(),
+                })?;
+        Ok(whisper_rs::get_lang_str(id).map(|code| DetectedLanguage {
+            code: code.to_string(),
+            probability: probs.get(id as usize).copied(),
+        }))
+    }
+
+    fn transcribe(
+        &mut self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        progress: &Progress,
+    ) -> Result<Transcript> {
+        let audio = read_pcm_16k_mono(&self.tools, wav, opts.verbose, progress)?;
+        let ctx = self.context(&opts.model)?;
+        let mut state = ctx
+            .create_state()
+            .map_err(|e| anyhow!("Failed to create whisper state: {e}"))?;
+
+        let d = &opts.decoding;
+        let strategy = match d.beam_size {
+            Some(beam_size) if beam_size > 1 => SamplingStrategy::BeamSearch {
+                beam_size: beam_size as i32,
+                patience: -1.0,
+            },
+            _ => SamplingStrategy::Greedy {
+                best_of: d.best_of.unwrap_or(1) as i32,
+            },
+        };
+        let mut params = FullParams::new(strategy);
+        params.set_language(Some(opts.language.as_str()));
+        params.set_translate(opts.translate);
+        params.set_token_timestamps(opts.word_timestamps);
+        if let Some(t) = opts.threads {
+            params.set_n_threads(t as i32);
+        }
+        if let Some(p) = &opts.prompt {
+            params.set_initial_prompt(p);
+        }
+        if let Some(t) = d.temperature {
+            params.set_temperature(t);
+        }
+        if let Some(inc) = d.effective_temperature_inc() {
+            params.set_temperature_inc(inc);
+        }
+        if let Some(v) = d.entropy_threshold {
+            params.set_entropy_thold(v);
+        }
+        if let Some(v) = d.logprob_threshold {
+            params.set_logprob_thold(v);
+        }
+        if let Some(v) = d.no_speech_threshold {
+            params.set_no_speech_thold(v);
+        }
+        if let Some(v) = d.max_len {
+            params.set_max_len(v as i32);
+        }
+        params.set_split_on_word(d.split_on_word);
+        if let Some(v) = d.max_context {
+            params.set_n_max_text_ctx(v as i32);
+        }
+        // d.processors: whisper-rs decodes with a single state, so it has no effect here
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+        let progress = progress.clone();
+        params
+            .set_progress_callback_safe(move |p: i32| progress.set_percent(p.clamp(0, 100) as u8));
+
+        state
+            .full(params, &audio)
+            .map_err(|e| Vid2txtError::Transcription {
+                message: format!("whisper inference failed: {e}"),
+                stderr: Vec::new(),
+            })?;
+
+        let language =
+            whisper_rs::get_lang_str(state.full_lang_id_from_state()).map(str::to_string);
+        let mut segments = Vec::new();
+        let (mut prob_sum, mut prob_count) = (0.0f32, 0usize);
+        for seg in state.as_iter() {
+            let mut words: Vec<Word> = Vec::new();
+            for i in 0..seg.n_tokens() {
+                let Some(tok) = seg.get_token(i) else {
+                    continue;
+                };
+                let text = tok.to_str_lossy().unwrap_or_default();
+                // special tokens: [_BEG_], [_TT_123], ...
+                if text.starts_with("[_") || text.is_empty() {
+                    continue;
+                }
+                prob_sum += tok.token_probability();
+                prob_count += 1;
+                if opts.word_timestamps {
+                    let data = tok.token_data();
+                    let (start, end) = (data.t0 as f64 / 100.0, data.t1 as f64 / 100.0);
+                    match words.last_mut() {
+                        Some(w) if !text.starts_with(' ') => {
+                            w.text.push_str(&text);
+                            w.end = end;
+                        }
+                        _ => words.push(Word {
+                            start,
+                            end,
+                            text: text.trim().to_string(),
+                            probability: Some(tok.token_probability()),
+                        }),
+                    }
+                }
+            }
+            segments.push(Segment {
+                // centiseconds
+                start: seg.start_timestamp() as f64 / 100.0,
+                end: seg.end_timestamp() as f64 / 100.0,
+                text: seg.to_str_lossy().unwrap_or_default().trim().to_string(),
+                speaker: None,
+                no_speech_prob: Some(seg.no_speech_probability()),
+                words,
+            });
+        }
+
+        Ok(Transcript {
+            language,
+            confidence: (prob_count > 0).then(|| prob_sum / prob_count as f32),
+            segments,
+            ..Default::default()
+        })
+    }
+}
+
+/// Load `wav` as 16 kHz mono f32 samples. Anything else (e.g. yt-dlp's 48 kHz stereo
+/// WAV) is resampled with ffmpeg first.
+fn read_pcm_16k_mono(
+    tools: &Tools,
+    wav: &Path,
+    verbose: bool,
+    progress: &Progress,
+) -> Result<Vec<f32>> {
+    let reader =
+        hound::WavReader::open(wav).with_context(|| format!("Failed to read {}", wav.display()))?;
+    let spec = reader.spec();
+    if spec.sample_rate == 16000
+        && spec.channels == 1
+        && spec.bits_per_sample == 16
+        && spec.sample_format == hound::SampleFormat::Int
+    {
+        return reader
+            .into_samples::<i16>()
+            .map(|s| Ok(s? as f32 / 32768.0))
+            .collect();
+    }
+
+    let temp = tempdir()?;
+    let _scratch = Partial::scratch(temp.path());
+    let converted = temp.path().join("audio-16k.wav");
+    let run = run_with_progress(
+        tools
+            .command(Tool::Ffmpeg)?
+            .arg("-y")
+            .arg("-i")
+            .arg(wav)
+            .args(["-acodec", "pcm_s16le", "-ar", "16000", "-ac", "1"])
+            .arg(&converted),
+        verbose,
+        progress,
+        |_| false,
+    )?;
+    if !run.status.success() {
+        return Err(Vid2txtError::Extraction {
+            message: format!("ffmpeg failed to resample {}", wav.display()),
+            stderr: run.stderr_tail,
+        }
+        .into());
+    }
+    let reader = hound::WavReader::open(&converted)?;
+    reader
+        .into_samples::<i16>()
+        .map(|s| Ok(s? as f32 / 32768.0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_wav(path: &Path, sample_rate: u32, channels: u16, samples: &[i16]) {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut w = hound::WavWriter::create(path, spec).unwrap();
+        for s in samples {
+            w.write_sample(*s).unwrap();
+        }
+        w.finalize().unwrap();
+    }
+
+    #[test]
+    fn reads_16k_mono_wavs_without_ffmpeg() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("a.wav");
+        write_wav(&wav, 16_000, 1, &[0, 16_384, -32_768]);
+        // no tools at all: nothing may need ffmpeg
+        let audio =
+            read_pcm_16k_mono(&Tools::default(), &wav, false, &Progress::stage("t", "")).unwrap();
+        assert_eq!(audio, [0.0, 0.5, -1.0]);
+    }
+
+    #[test]
+    fn other_wavs_need_ffmpeg() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("a.wav");
+        write_wav(&wav, 48_000, 2, &[0; 96]);
+        let err = read_pcm_16k_mono(&Tools::default(), &wav, false, &Progress::stage("t", ""))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Vid2txtError>(),
+            Some(Vid2txtError::MissingTool(_))
+        ));
+    }
+
+    #[test]
+    fn declares_its_capabilities() {
+        let backend = InProcessBackend::new(Tools::default());
+        let caps = backend.capabilities();
+        assert!(caps.translate && caps.word_timestamps && caps.decoding);
+        assert!(!caps.diarization);
+        assert!(backend.uses_local_model());
+        assert!(backend.required_tools().is_empty());
+    }
+}
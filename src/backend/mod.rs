@@ -0,0 +1,263 @@
+//! Transcription engines. Acquisition (yt-dlp/ffmpeg) produces a 16 kHz mono WAV;
+//! a [`Transcriber`] turns it into a [`Transcript`].
+
+use crate::error::Vid2txtError;
+use crate::progress::Progress;
+use crate::tools::{Tool, Tools};
+use crate::transcript::Transcript;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+mod decoding;
+#[cfg(feature = "in-process")]
+mod in_process;
+mod openai;
+mod verbose_json;
+mod whisper_cli;
+mod whisper_server;
+
+pub use decoding::{DecodingParams, DecodingPreset};
+#[cfg(feature = "in-process")]
+pub use in_process::InProcessBackend;
+pub use openai::OpenAiBackend;
+pub use whisper_cli::{WHISPER_CLI_FLAGS, WhisperCliBackend};
+pub use whisper_server::WhisperServerBackend;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendKind {
+    /// Spawn whisper.cpp's whisper-cli per file
+    WhisperCli,
+    /// Keep the model loaded in whisper.cpp's whisper-server across a batch
+    WhisperServer,
+    /// OpenAI-compatible /audio/transcriptions API (--api-base-url)
+    Openai,
+    /// whisper.cpp linked into vid2txt (requires the `in-process` cargo feature)
+    InProcess,
+}
+
+/// `response_format` requested from OpenAI-compatible servers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ApiResponseFormat {
+    /// Segments with timings (needed for --word-timestamps)
+    VerboseJson,
+    /// Text only, as JSON
+    Json,
+    /// Plain text
+    Text,
+}
+
+impl ApiResponseFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            ApiResponseFormat::VerboseJson => "verbose_json",
+            ApiResponseFormat::Json => "json",
+            ApiResponseFormat::Text => "text",
+        }
+    }
+}
+
+/// Backend-specific settings from the command line.
+#[derive(Debug, Clone)]
+pub struct BackendSettings {
+    /// Existing whisper-server to use instead of spawning one
+    pub server_url: Option<String>,
+    /// OpenAI-compatible API base, e.g. `http://gpu-box:8000/v1`
+    pub api_base_url: Option<String>,
+    pub api_key: Option<String>,
+    /// Model name sent to the API
+    pub api_model: String,
+    pub api_response_format: ApiResponseFormat,
+    pub tools: Tools,
+}
+
+/// Optional features a backend may or may not implement.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Capabilities {
+    pub translate: bool,
+    pub word_timestamps: bool,
+    pub diarization: bool,
+    /// Honours every [`DecodingParams`] field (OpenAI-style APIs only take a temperature)
+    pub decoding: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TranscribeOptions {
+    /// Model resolved by `models::resolve_or_download_model`
+    pub model: PathBuf,
+    /// Language code, or "auto"
+    pub language: String,
+    pub threads: Option<u32>,
+    /// Initial prompt to bias recognition
+    pub prompt: Option<String>,
+    /// Translate to English instead of transcribing
+    pub translate: bool,
+    pub word_timestamps: bool,
+    pub diarize: bool,
+    pub decoding: DecodingParams,
+    pub verbose: bool,
+}
+
+/// Stands in for a per-run temporary directory in `--dry-run` output (the real
+/// one gets a random suffix).
+pub fn temp_dir_placeholder() -> PathBuf {
+    std::env::temp_dir().join(".tmpXXXXXX")
+}
+
+/// The engine could not fit the model in memory; a smaller model may work.
+#[derive(Debug)]
+pub struct OutOfMemory {
+    pub backend: &'static str,
+}
+
+impl std::fmt::Display for OutOfMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ran out of memory", self.backend)
+    }
+}
+
+impl std::error::Error for OutOfMemory {}
+
+/// Classify a failed HTTP request: the server being unreachable is a network
+/// problem, an error status from it a failed transcription.
+pub(crate) fn request_error(e: reqwest::Error, what: String) -> anyhow::Error {
+    let kind = if e.is_connect() || e.is_timeout() {
+        Vid2txtError::Network(what)
+    } else {
+        Vid2txtError::Transcription {
+            message: what,
+            stderr: Vec::new(),
+        }
+    };
+    anyhow::Error::new(e).context(kind)
+}
+
+/// Whether a whisper.cpp log line reports a failed allocation.
+pub fn is_oom_message(line: &str) -> bool {
+    let l = line.to_lowercase();
+    [
+        "failed to allocate",
+        "out of memory",
+        "std::bad_alloc",
+        "insufficient memory",
+    ]
+    .iter()
+    .any(|m| l.contains(m))
+}
+
+/// Outcome of a language detection pass.
+#[derive(Debug, Clone)]
+pub struct DetectedLanguage {
+    /// whisper language code, e.g. "de"
+    pub code: String,
+    pub probability: Option<f32>,
+}
+
+pub trait Transcriber {
+    fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// External binaries that must be installed.
+    fn required_tools(&self) -> Vec<Tool> {
+        Vec::new()
+    }
+
+    /// What [`Self::detect_language`] (with `detect_seconds`) and [`Self::transcribe`]
+    /// would run, one line per command or request, for `--dry-run`.
+    fn plan(
+        &self,
+        _wav: &Path,
+        _opts: &TranscribeOptions,
+        _detect_seconds: Option<u32>,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Whether `TranscribeOptions::model` must point at a local ggml file.
+    fn uses_local_model(&self) -> bool {
+        true
+    }
+
+    /// Identify the spoken language from the first `seconds` of `wav`. `None` when
+    /// the backend has no separate detection step (it may still report the
+    /// language in its [`Transcript`]).
+    fn detect_language(
+        &mut self,
+        _wav: &Path,
+        _opts: &TranscribeOptions,
+        _seconds: u32,
+        _progress: &Progress,
+    ) -> Result<Option<DetectedLanguage>> {
+        Ok(None)
+    }
+
+    /// Backends that can't report progress leave `progress` as a spinner.
+    fn transcribe(
+        &mut self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        progress: &Progress,
+    ) -> Result<Transcript>;
+}
+
+/// Reject options the backend can't honour before any work is done.
+pub fn check_capabilities(backend: &dyn Transcriber, opts: &TranscribeOptions) -> Result<()> {
+    let caps = backend.capabilities();
+    let unsupported: Vec<&str> = [
+        (opts.translate && !caps.translate, "--translate"),
+        (
+            opts.word_timestamps && !caps.word_timestamps,
+            "--word-timestamps",
+        ),
+        (opts.diarize && !caps.diarization, "--diarize"),
+    ]
+    .into_iter()
+    .filter_map(|(bad, flag)| bad.then_some(flag))
+    .collect();
+
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        Err(Vid2txtError::InvalidInput(format!(
+            "The {} backend does not support {}",
+            backend.name(),
+            unsupported.join(", ")
+        ))
+        .into())
+    }
+}
+
+pub fn create_backend(
+    kind: BackendKind,
+    settings: BackendSettings,
+) -> Result<Box<dyn Transcriber>> {
+    Ok(match kind {
+        BackendKind::WhisperCli => Box::new(WhisperCliBackend::new(settings.tools)),
+        BackendKind::WhisperServer => Box::new(WhisperServerBackend::new(
+            settings.server_url,
+            settings.tools,
+        )),
+        BackendKind::Openai => {
+            let base_url = settings.api_base_url.ok_or_else(|| {
+                Vid2txtError::InvalidInput("--backend openai requires --api-base-url".into())
+            })?;
+            Box::new(OpenAiBackend::new(
+                base_url,
+                settings.api_key,
+                settings.api_model,
+                settings.api_response_format,
+            ))
+        }
+        #[cfg(feature = "in-process")]
+        BackendKind::InProcess => Box::new(InProcessBackend::new(settings.tools)),
+        #[cfg(not(feature = "in-process"))]
+        BackendKind::InProcess => {
+            return Err(anyhow::anyhow!(
+                "This vid2txt was built without the in-process backend (cargo build --features in-process)"
+            ));
+        }
+    })
+}
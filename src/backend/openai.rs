@@ -0,0 +1,259 @@
+use super::verbose_json;
+use super::{ApiResponseFormat, Capabilities, TranscribeOptions, Transcriber, request_error};
+use crate::interrupt;
+use crate::progress::Progress;
+use crate::transcript::{Segment, Transcript};
+use anyhow::{Context, Result};
+use reqwest::blocking::{Client, multipart::Form};
+use std::path::Path;
+
+/// Posts the WAV to an OpenAI-compatible `/audio/transcriptions` endpoint
+/// (faster-whisper servers, speaches, LocalAI, ...).
+pub struct OpenAiBackend {
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    response_format: ApiResponseFormat,
+    client: Client,
+}
+
+impl OpenAiBackend {
+    pub fn new(
+        base_url: String,
+        api_key: Option<String>,
+        model: String,
+        response_format: ApiResponseFormat,
+    ) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model,
+            response_format,
+            // long files take a while server-side
+            client: Client::builder()
+                .timeout(None)
+                .build()
+                .expect("HTTP client"),
+        }
+    }
+}
+
+impl Transcriber for OpenAiBackend {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            translate: true,
+            // timings only come with verbose_json
+            word_timestamps: self.response_format == ApiResponseFormat::VerboseJson,
+            diarization: false,
+            decoding: false,
+        }
+    }
+
+    fn uses_local_model(&self) -> bool {
+        false
+    }
+
+    fn plan(
+        &self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        _detect_seconds: Option<u32>,
+    ) -> Result<Vec<String>> {
+        let endpoint = if opts.translate {
+            "translations"
+        } else {
+            "transcriptions"
+        };
+        Ok(vec![format!(
+            "POST {}/audio/{endpoint} file={} model={} response_format={}",
+            self.base_url,
+            wav.display(),
+            self.model,
+            self.response_format.as_str()
+        )])
+    }
+
+    fn transcribe(
+        &mut self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        _progress: &Progress,
+    ) -> Result<Transcript> {
+        // translations always produce English and take no language
+        let endpoint = if opts.translate {
+            "translations"
+        } else {
+            "transcriptions"
+        };
+        let url = format!("{}/audio/{endpoint}", self.base_url);
+
+        let mut form = Form::new()
+            .file("file", wav)
+            .with_context(|| format!("Failed to read {}", wav.display()))?
+            .text("model", self.model.clone())
+            .text("response_format", self.response_format.as_str());
+        if !opts.translate && opts.language != "auto" {
+            form = form.text("language", opts.language.clone());
+        }
+        if let Some(p) = &opts.prompt {
+            form = form.text("prompt", p.clone());
+        }
+        if let Some(t) = opts.decoding.temperature {
+            form = form.text("temperature", t.to_string());
+        }
+        if opts.word_timestamps {
+            form = form
+                .text("timestamp_granularities[]", "segment")
+                .text("timestamp_granularities[]", "word");
+        }
+
+        let mut req = self.client.post(&url).multipart(form);
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+        let (status, body) = interrupt::interruptible(move || {
+            let resp = req.send()?;
+            Ok((resp.status(), resp.bytes()?))
+        })?
+        .map_err(|e| request_error(e, format!("Transcription request to {url} failed")))?;
+        if !status.is_success() {
+            // OpenAI-style APIs explain failures in an {"error": ...} body
+            let msg = serde_json::from_slice(&body)
+                .ok()
+                .and_then(|v| verbose_json::error_message(&v))
+                .unwrap_or_else(|| format!("{url} replied {status}"));
+            return Err(verbose_json::server_error(&msg));
+        }
+
+        match self.response_format {
+            ApiResponseFormat::Text => Ok(Transcript {
+                segments: vec![Segment {
+                    text: String::from_utf8_lossy(&body).trim().to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            _ => Ok(verbose_json::parse(&body)?.into_transcript(opts.word_timestamps)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Vid2txtError;
+    use crate::mock_server::MockServer;
+
+    fn transcribe(
+        status: u16,
+        body: &'static str,
+        format: ApiResponseFormat,
+        opts: &TranscribeOptions,
+    ) -> (Result<Transcript>, MockServer) {
+        let content_type = match format {
+            ApiResponseFormat::Text => "text/plain",
+            _ => "application/json",
+        };
+        let server = MockServer::start(move |_| (status, content_type, body.into()));
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("a.wav");
+        std::fs::write(&wav, b"RIFF").unwrap();
+        let mut backend = OpenAiBackend::new(
+            format!("{}/v1/", server.url),
+            Some("sk-test".into()),
+            "whisper-1".into(),
+            format,
+        );
+        let result = backend.transcribe(&wav, opts, &Progress::stage("test", ""));
+        (result, server)
+    }
+
+    fn auto() -> TranscribeOptions {
+        TranscribeOptions {
+            language: "auto".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reads_text_replies() {
+        let (t, server) = transcribe(200, " Hello there.\n", ApiResponseFormat::Text, &auto());
+        let t = t.unwrap();
+        assert_eq!(t.segments.len(), 1);
+        assert_eq!(t.segments[0].text, "Hello there.");
+        let req = &server.requests()[0];
+        assert_eq!(req.path, "/v1/audio/transcriptions");
+        assert_eq!(req.header("authorization"), Some("Bearer sk-test"));
+    }
+
+    #[test]
+    fn reads_json_replies() {
+        let opts = TranscribeOptions {
+            translate: true,
+            ..auto()
+        };
+        let (t, server) = transcribe(
+            200,
+            r#"{"text":" Guten Tag. "}"#,
+            ApiResponseFormat::Json,
+            &opts,
+        );
+        let t = t.unwrap();
+        assert_eq!(t.segments[0].text, "Guten Tag.");
+        assert_eq!(t.language, None);
+        assert_eq!(server.requests()[0].path, "/v1/audio/translations");
+    }
+
+    #[test]
+    fn reads_verbose_json_replies() {
+        let opts = TranscribeOptions {
+            word_timestamps: true,
+            ..auto()
+        };
+        let (t, _server) = transcribe(
+            200,
+            r#"{"language":"english","text":"Hi. Bye.",
+                "segments":[{"start":0.0,"end":1.0,"text":" Hi.","avg_logprob":0.0},
+                            {"start":1.0,"end":2.5,"text":" Bye.","no_speech_prob":0.1}],
+                "words":[{"word":"Hi.","start":0.1,"end":0.5},
+                         {"word":" Bye.","start":1.2,"end":2.0}]}"#,
+            ApiResponseFormat::VerboseJson,
+            &opts,
+        );
+        let t = t.unwrap();
+        assert_eq!(t.language.as_deref(), Some("en"));
+        assert_eq!(t.confidence, Some(1.0));
+        assert_eq!(t.segments.len(), 2);
+        assert_eq!(t.segments[1].text, "Bye.");
+        assert_eq!(t.segments[1].no_speech_prob, Some(0.1));
+        assert_eq!(t.segments[0].words[0].text, "Hi.");
+        assert_eq!(t.segments[1].words[0].text, "Bye.");
+    }
+
+    fn server_error(status: u16, body: &'static str) -> String {
+        let (t, _server) = transcribe(status, body, ApiResponseFormat::VerboseJson, &auto());
+        let err = t.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Vid2txtError>(),
+            Some(Vid2txtError::Transcription { .. })
+        ));
+        format!("{err:#}")
+    }
+
+    #[test]
+    fn reports_error_replies() {
+        assert!(server_error(200, r#"{"error":"model not loaded"}"#).contains("model not loaded"));
+        assert!(
+            server_error(
+                400,
+                r#"{"error":{"message":"Invalid file format","type":"x"}}"#
+            )
+            .contains("Invalid file format")
+        );
+        assert!(server_error(502, "Bad Gateway").contains("502"));
+    }
+}
@@ -0,0 +1,179 @@
+//! The OpenAI-style `verbose_json` response, as returned by whisper-server's
+//! `/inference` and by `/v1/audio/transcriptions` compatible servers.
+
+use crate::error::Vid2txtError;
+use crate::language;
+use crate::transcript::{Segment, Transcript, Word};
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct VerboseJson {
+    #[serde(default)]
+    language: Option<String>,
+    /// whisper-server reports the code here and the full name in `language`
+    #[serde(default)]
+    detected_language: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    segments: Vec<VjSegment>,
+    /// OpenAI puts word timings at the top level, whisper-server per segment
+    #[serde(default)]
+    words: Vec<VjWord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VjSegment {
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(default)]
+    no_speech_prob: Option<f32>,
+    #[serde(default)]
+    avg_logprob: Option<f32>,
+    #[serde(default)]
+    words: Vec<VjWord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VjWord {
+    word: String,
+    start: f64,
+    end: f64,
+    #[serde(default)]
+    probability: Option<f32>,
+}
+
+impl From<VjWord> for Word {
+    fn from(w: VjWord) -> Self {
+        Word {
+            start: w.start,
+            end: w.end,
+            text: w.word.trim().to_string(),
+            probability: w.probability,
+        }
+    }
+}
+
+/// Parse a response body, turning `{"error": ...}` replies into errors.
+pub fn parse(body: &[u8]) -> Result<VerboseJson> {
+    let value: serde_json::Value = serde_json::from_slice(body)
+        .map_err(|e| anyhow!("Unexpected transcription response ({e})"))?;
+    if let Some(msg) = error_message(&value) {
+        return Err(server_error(&msg));
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// The message of an `{"error": "..."}` or `{"error": {"message": "..."}}` body.
+pub fn error_message(value: &serde_json::Value) -> Option<String> {
+    let err = value.get("error")?;
+    Some(
+        err.get("message")
+            .and_then(|m| m.as_str())
+            .or_else(|| err.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| err.to_string()),
+    )
+}
+
+pub fn server_error(msg: &str) -> anyhow::Error {
+    Vid2txtError::Transcription {
+        message: format!("Transcription server error: {msg}"),
+        stderr: Vec::new(),
+    }
+    .into()
+}
+
+impl VerboseJson {
+    pub fn into_transcript(self, word_timestamps: bool) -> Transcript {
+        // avg_logprob is per token, so exp() gives a (geometric) mean probability
+        let logprobs: Vec<f32> = self.segments.iter().filter_map(|s| s.avg_logprob).collect();
+        let confidence = (!logprobs.is_empty())
+            .then(|| (logprobs.iter().sum::<f32>() / logprobs.len() as f32).exp());
+        let mut top_words = self.words.into_iter().peekable();
+        let mut segments: Vec<Segment> = self
+            .segments
+            .into_iter()
+            .map(|s| {
+                let mut words: Vec<Word> = s.words.into_iter().map(Word::from).collect();
+                // hand out top-level words that start before this segment ends
+                while let Some(w) = top_words.next_if(|w| w.start < s.end) {
+                    words.push(w.into());
+                }
+                Segment {
+                    start: s.start,
+                    end: s.end,
+                    text: s.text.trim().to_string(),
+                    speaker: None,
+                    no_speech_prob: s.no_speech_prob,
+                    words: if word_timestamps { words } else { Vec::new() },
+                }
+            })
+            .collect();
+
+        // Plain `json`-like replies carry only the text
+        if segments.is_empty()
+            && let Some(text) = self.text.filter(|t| !t.trim().is_empty())
+        {
+            segments.push(Segment {
+                text: text.trim().to_string(),
+                ..Default::default()
+            });
+        }
+
+        // whisper-server and some APIs name the language ("english"); keep codes
+        let language = [self.detected_language, self.language]
+            .into_iter()
+            .flatten()
+            .find_map(|l| language::to_code(&l))
+            .map(str::to_string);
+        Transcript {
+            language,
+            confidence,
+            segments,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(body: &str) -> Option<String> {
+        parse(body.as_bytes())
+            .unwrap()
+            .into_transcript(false)
+            .language
+    }
+
+    #[test]
+    fn normalizes_the_language_to_a_code() {
+        assert_eq!(language(r#"{"language":"english"}"#).as_deref(), Some("en"));
+        assert_eq!(
+            language(r#"{"language":"german","detected_language":"de"}"#).as_deref(),
+            Some("de")
+        );
+        assert_eq!(language(r#"{"language":"EN"}"#).as_deref(), Some("en"));
+        assert_eq!(language(r#"{"language":"klingon"}"#), None);
+        assert_eq!(language("{}"), None);
+    }
+
+    #[test]
+    fn drops_words_unless_asked() {
+        let body = r#"{"segments":[{"start":0,"end":1,"text":"a",
+            "words":[{"word":"a","start":0,"end":1}]}]}"#;
+        let t = parse(body.as_bytes()).unwrap().into_transcript(false);
+        assert!(t.segments[0].words.is_empty());
+        let t = parse(body.as_bytes()).unwrap().into_transcript(true);
+        assert_eq!(t.segments[0].words.len(), 1);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(parse(b"<html>").is_err());
+        assert!(parse(br#"{"error":{"message":"busy"}}"#).is_err());
+    }
+}
@@ -0,0 +1,407 @@
+use super::{
+    Capabilities, DetectedLanguage, OutOfMemory, TranscribeOptions, Transcriber, is_oom_message,
+    temp_dir_placeholder,
+};
+use crate::error::Vid2txtError;
+use crate::interrupt::Partial;
+use crate::progress::{Progress, run_with_progress};
+use crate::tools::{Tool, Tools, command_line};
+use crate::transcript::{Segment, Transcript, Word};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+use tempfile::tempdir;
+
+/// Flags of `whisper-cli --help` vid2txt passes, with what needs them; `true` marks
+/// flags every run uses. Older builds lack some of them and reject the whole call.
+pub const WHISPER_CLI_FLAGS: &[(&str, &str, bool)] = &[
+    ("-ojf", "the JSON transcript vid2txt reads", true),
+    ("-of", "choosing the output file", true),
+    ("-pp", "the progress bar", true),
+    ("-dl", "language detection (--language auto)", false),
+    ("--prompt", "--prompt and --glossary", false),
+    ("-bs", "--beam-size and --preset", false),
+    ("-tpi", "--temperature-inc and --preset", false),
+    ("-nth", "--no-speech-threshold", false),
+    ("-sow", "--split-on-word", false),
+    ("-di", "--diarize", false),
+];
+
+/// Runs whisper.cpp's `whisper-cli` once per file and reads its full JSON output.
+pub struct WhisperCliBackend {
+    tools: Tools,
+}
+
+impl WhisperCliBackend {
+    pub fn new(tools: Tools) -> Self {
+        Self { tools }
+    }
+
+    /// -dl: stop after "auto-detected language: de (p = 0.931)"; -d limits the audio (ms)
+    fn detect_command(
+        &self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        seconds: u32,
+    ) -> Result<Command> {
+        let mut whisper = self.tools.command(Tool::WhisperCli)?;
+        whisper.arg("-m").arg(&opts.model);
+        whisper.arg("-f").arg(wav);
+        whisper.args(["-l", "auto", "-dl"]);
+        whisper
+            .arg("-d")
+            .arg((u64::from(seconds) * 1000).to_string());
+        if let Some(t) = opts.threads {
+            whisper.arg("-t").arg(t.to_string());
+        }
+        Ok(whisper)
+    }
+
+    /// whisper-cli flags: -m <model> -f <wav> -ojf -of <output_base> -l <lang> [-t <threads>]
+    fn transcribe_command(
+        &self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        output_base: &Path,
+    ) -> Result<Command> {
+        let mut whisper = self.tools.command(Tool::WhisperCli)?;
+        whisper.arg("-m").arg(&opts.model);
+        whisper.arg("-f").arg(wav);
+        whisper.arg("-ojf");
+        whisper.arg("-of").arg(output_base);
+        whisper.arg("-l").arg(&opts.language);
+        if let Some(t) = opts.threads {
+            whisper.arg("-t").arg(t.to_string());
+        }
+        if let Some(p) = &opts.prompt {
+            whisper.arg("--prompt").arg(p);
+        }
+        if opts.translate {
+            whisper.arg("-tr");
+        }
+        if opts.diarize {
+            whisper.arg("-di");
+        }
+        whisper.args(opts.decoding.whisper_cpp_args());
+        // -pp: "whisper_print_progress_callback: progress =  42%" on stderr
+        whisper.arg("-pp");
+        Ok(whisper)
+    }
+}
+
+impl Transcriber for WhisperCliBackend {
+    fn name(&self) -> &'static str {
+        "whisper-cli"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            translate: true,
+            // token timings from -ojf
+            word_timestamps: true,
+            // -di: stereo channel diarization
+            diarization: true,
+            decoding: true,
+        }
+    }
+
+    fn required_tools(&self) -> Vec<Tool> {
+        vec![Tool::WhisperCli]
+    }
+
+    fn plan(
+        &self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        detect_seconds: Option<u32>,
+    ) -> Result<Vec<String>> {
+        let mut plan = Vec::new();
+        if let Some(seconds) = detect_seconds {
+            plan.push(command_line(&self.detect_command(wav, opts, seconds)?));
+        }
+        let output_base = temp_dir_placeholder().join("transcript");
+        plan.push(command_line(&self.transcribe_command(
+            wav,
+            opts,
+            &output_base,
+        )?));
+        Ok(plan)
+    }
+
+    fn detect_language(
+        &mut self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        seconds: u32,
+        progress: &Progress,
+    ) -> Result<Option<DetectedLanguage>> {
+        let mut whisper = self.detect_command(wav, opts, seconds)?;
+        let mut detected = None;
+        let run = run_with_progress(&mut whisper, opts.verbose, progress, |line| {
+            match parse_detected_language(line) {
+                Some(language) => {
+                    detected = Some(language);
+                    opts.verbose
+                }
+                None => false,
+            }
+        })?;
+        if !run.status.success() {
+            return Err(Vid2txtError::Transcription {
+                message: "whisper-cli language detection failed".into(),
+                stderr: run.stderr_tail,
+            }
+            .into());
+        }
+        match detected {
+            Some(language) => Ok(Some(language)),
+            None => Err(Vid2txtError::Transcription {
+                message: "whisper-cli did not report a detected language (builds without -dl are too old; see `vid2txt doctor`)".into(),
+                stderr: run.stderr_tail,
+            }
+            .into()),
+        }
+    }
+
+    fn transcribe(
+        &mut self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        progress: &Progress,
+    ) -> Result<Transcript> {
+        let temp = tempdir()?;
+        let _scratch = Partial::scratch(temp.path());
+        let output_base = temp.path().join("transcript");
+        let mut whisper = self.transcribe_command(wav, opts, &output_base)?;
+
+        let mut oom = false;
+        let mut unknown_arg = None;
+        let run = run_with_progress(&mut whisper, opts.verbose, progress, |line| {
+            oom |= is_oom_message(line);
+            // older builds print this plus the usage, then exit 0
+            if let Some((_, arg)) = line.split_once("unknown argument:") {
+                unknown_arg.get_or_insert_with(|| arg.trim().to_string());
+            }
+            match parse_progress(line) {
+                Some(p) => {
+                    progress.set_percent(p);
+                    true
+                }
+                None => false,
+            }
+        })?;
+        if !run.status.success() {
+            if oom || killed_by_oom_killer(&run.status) {
+                return Err(OutOfMemory {
+                    backend: "whisper-cli",
+                }
+                .into());
+            }
+            return Err(Vid2txtError::Transcription {
+                message: format!("whisper-cli failed ({})", run.status),
+                stderr: run.stderr_tail,
+            }
+            .into());
+        }
+        if let Some(arg) = unknown_arg {
+            return Err(Vid2txtError::MissingTool(format!(
+                "whisper-cli does not support {arg}; it is probably too old (see `vid2txt doctor`)"
+            ))
+            .into());
+        }
+
+        let json_path = output_base.with_extension("json");
+        let bytes = fs::read(&json_path)
+            .with_context(|| format!("whisper-cli ran, but wrote no {}", json_path.display()))?;
+        let parsed: CliJson = serde_json::from_slice(&bytes)
+            .with_context(|| format!("Unexpected whisper-cli JSON in {}", json_path.display()))?;
+        Ok(parsed.into_transcript(opts.word_timestamps))
+    }
+}
+
+/// The result of a `-dl` run, e.g.
+/// `whisper_full_with_state: auto-detected language: de (p = 0.981742)`.
+fn parse_detected_language(line: &str) -> Option<DetectedLanguage> {
+    let (_, rest) = line.split_once("auto-detected language:")?;
+    let (code, p) = rest.split_once('(').unwrap_or((rest, ""));
+    Some(DetectedLanguage {
+        code: code.trim().to_string(),
+        probability: p
+            .trim_start_matches("p =")
+            .trim()
+            .trim_end_matches(')')
+            .parse()
+            .ok(),
+    })
+}
+
+/// The percentage in a `-pp` line, e.g.
+/// `whisper_print_progress_callback: progress =  42%`.
+fn parse_progress(line: &str) -> Option<u8> {
+    line.split_once("progress =")
+        .and_then(|(_, p)| p.trim().trim_end_matches('%').parse().ok())
+}
+
+/// SIGKILL is what the kernel's OOM killer sends.
+#[cfg(unix)]
+fn killed_by_oom_killer(status: &ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal() == Some(9)
+}
+
+#[cfg(not(unix))]
+fn killed_by_oom_killer(_status: &ExitStatus) -> bool {
+    false
+}
+
+#[derive(Debug, Deserialize)]
+struct CliJson {
+    #[serde(default)]
+    result: CliResult,
+    #[serde(default)]
+    transcription: Vec<CliSegment>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CliResult {
+    #[serde(default)]
+    language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CliSegment {
+    offsets: CliOffsets,
+    text: String,
+    #[serde(default)]
+    tokens: Vec<CliToken>,
+    #[serde(default)]
+    speaker: Option<String>,
+}
+
+/// Milliseconds
+#[derive(Debug, Deserialize)]
+struct CliOffsets {
+    from: i64,
+    to: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CliToken {
+    text: String,
+    offsets: CliOffsets,
+    #[serde(default)]
+    p: Option<f32>,
+}
+
+impl CliJson {
+    fn into_transcript(self, word_timestamps: bool) -> Transcript {
+        let probs: Vec<f32> = self
+            .transcription
+            .iter()
+            .flat_map(|s| &s.tokens)
+            .filter(|t| !t.text.starts_with("[_"))
+            .filter_map(|t| t.p)
+            .collect();
+        let segments = self
+            .transcription
+            .into_iter()
+            .map(|s| Segment {
+                start: s.offsets.from as f64 / 1000.0,
+                end: s.offsets.to as f64 / 1000.0,
+                words: if word_timestamps {
+                    tokens_to_words(&s.tokens)
+                } else {
+                    Vec::new()
+                },
+                text: s.text.trim().to_string(),
+                speaker: s.speaker,
+                // not part of whisper-cli's JSON
+                no_speech_prob: None,
+            })
+            .collect();
+        Transcript {
+            language: self.result.language,
+            confidence: mean(&probs),
+            segments,
+            ..Default::default()
+        }
+    }
+}
+
+/// Merge sub-word tokens into words: a token starting with a space begins a new word.
+/// Special tokens (`[_BEG_]`, `[_TT_123]`, ...) are skipped.
+fn tokens_to_words(tokens: &[CliToken]) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::new();
+    let mut probs: Vec<f32> = Vec::new();
+    for t in tokens {
+        if t.text.starts_with("[_") || t.text.is_empty() {
+            continue;
+        }
+        let starts_word = t.text.starts_with(' ') || words.is_empty();
+        if starts_word {
+            if let Some(w) = words.last_mut() {
+                w.probability = mean(&probs);
+            }
+            probs.clear();
+            words.push(Word {
+                start: t.offsets.from as f64 / 1000.0,
+                end: t.offsets.to as f64 / 1000.0,
+                text: t.text.trim().to_string(),
+                probability: None,
+            });
+        } else if let Some(w) = words.last_mut() {
+            w.text.push_str(&t.text);
+            w.end = t.offsets.to as f64 / 1000.0;
+        }
+        probs.extend(t.p);
+    }
+    if let Some(w) = words.last_mut() {
+        w.probability = mean(&probs);
+    }
+    words
+}
+
+fn mean(v: &[f32]) -> Option<f32> {
+    (!v.is_empty()).then(|| v.iter().sum::<f32>() / v.len() as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_detected_language() {
+        let detected = parse_detected_language(
+            "whisper_full_with_state: auto-detected language: de (p = 0.981742)",
+        )
+        .unwrap();
+        assert_eq!(detected.code, "de");
+        assert_eq!(detected.probability, Some(0.981742));
+        let detected = parse_detected_language("auto-detected language: en").unwrap();
+        assert_eq!(detected.code, "en");
+        assert_eq!(detected.probability, None);
+        assert!(parse_detected_language("whisper_init_state: kv self size = 6.29 MB").is_none());
+    }
+
+    #[test]
+    fn parses_progress_lines() {
+        for (line, percent) in [
+            ("whisper_print_progress_callback: progress =   5%", Some(5)),
+            ("whisper_print_progress_callback: progress =  42%", Some(42)),
+            (
+                "whisper_print_progress_callback: progress = 100%",
+                Some(100),
+            ),
+            ("whisper_full_with_state: progress = 15%", Some(15)),
+            (
+                "whisper_init_from_file_with_params_no_state: loading model",
+                None,
+            ),
+            ("[00:00:00.000 --> 00:00:02.000]   progress = fine", None),
+        ] {
+            assert_eq!(parse_progress(line), percent, "{line}");
+        }
+    }
+}
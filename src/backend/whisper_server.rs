@@ -0,0 +1,352 @@
+use super::verbose_json;
+use super::{
+    Capabilities, DecodingParams, OutOfMemory, TranscribeOptions, Transcriber, is_oom_message,
+    request_error,
+};
+use crate::constants::STDERR_TAIL_LINES;
+use crate::error::Vid2txtError;
+use crate::interrupt::{self, ChildGuard};
+use crate::logging;
+use crate::progress::Progress;
+use crate::report::say;
+use crate::tools::{Tool, Tools, command_line};
+use crate::transcript::Transcript;
+use anyhow::{Context, Result};
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, multipart::Form};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+
+/// Loading a large model from a slow disk can take a while.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(300);
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Sends audio to whisper.cpp's `whisper-server`, either one we spawn (and keep for
+/// the whole batch, so the model is loaded once) or an already running one.
+pub struct WhisperServerBackend {
+    external_url: Option<String>,
+    server: Option<ServerProcess>,
+    client: Client,
+    tools: Tools,
+}
+
+/// A spawned whisper-server; killed when dropped.
+struct ServerProcess {
+    child: Child,
+    _group: ChildGuard,
+    model: PathBuf,
+    base_url: String,
+    /// Captured stdout/stderr, unless it goes straight to the terminal (`-v`
+    /// without `--log-file`)
+    log: Option<NamedTempFile>,
+    /// How much of `log` has been copied to the log file
+    logged: u64,
+    verbose: bool,
+}
+
+impl ServerProcess {
+    /// Copy server output captured since the last call to the log file (and to the
+    /// terminal with `-v`).
+    fn flush_log(&mut self) {
+        let Some(log) = &self.log else {
+            return;
+        };
+        let Ok(mut file) = File::open(log.path()) else {
+            return;
+        };
+        let mut new = Vec::new();
+        if file.seek(SeekFrom::Start(self.logged)).is_err() || file.read_to_end(&mut new).is_err() {
+            return;
+        }
+        // only whole lines; a partial one waits for the next call
+        let Some(end) = new.iter().rposition(|b| *b == b'\n') else {
+            return;
+        };
+        self.logged += end as u64 + 1;
+        for line in String::from_utf8_lossy(&new[..end]).lines() {
+            logging::child_line("whisper-server", true, line);
+            if self.verbose {
+                eprintln!("{}{line}", logging::console_prefix());
+            }
+        }
+    }
+}
+
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        if let Ok(status) = self.child.wait() {
+            self.flush_log();
+            logging::child_exit("whisper-server", &status);
+        }
+    }
+}
+
+impl WhisperServerBackend {
+    pub fn new(external_url: Option<String>, tools: Tools) -> Self {
+        Self {
+            external_url: external_url.map(|u| u.trim_end_matches('/').to_string()),
+            server: None,
+            // inference on long files easily exceeds reqwest's default timeout
+            client: Client::builder()
+                .timeout(None)
+                .build()
+                .expect("HTTP client"),
+            tools,
+        }
+    }
+
+    fn server_command(&self, opts: &TranscribeOptions, port: &str) -> Result<Command> {
+        let mut cmd = self.tools.command(Tool::WhisperServer)?;
+        cmd.arg("-m").arg(&opts.model);
+        cmd.arg("--host").arg("127.0.0.1");
+        cmd.arg("--port").arg(port);
+        if let Some(t) = opts.threads {
+            cmd.arg("-t").arg(t.to_string());
+        }
+        // the remaining decoding params are sent per request
+        if let Some(p) = opts.decoding.processors {
+            cmd.arg("-p").arg(p.to_string());
+        }
+        Ok(cmd)
+    }
+
+    /// Base URL of a ready server with `opts.model` loaded, spawning one if needed.
+    fn ensure_server(&mut self, opts: &TranscribeOptions) -> Result<String> {
+        if let Some(url) = &self.external_url {
+            return Ok(url.clone());
+        }
+        if let Some(s) = &self.server
+            && s.model == opts.model
+        {
+            return Ok(s.base_url.clone());
+        }
+        // different model: shut the old server down first
+        self.server = None;
+
+        let port = free_port()?;
+        let base_url = format!("http://127.0.0.1:{port}");
+        let mut cmd = self.server_command(opts, &port.to_string())?;
+
+        let log = if opts.verbose && !logging::file_enabled() {
+            None
+        } else {
+            let log = NamedTempFile::new()?;
+            cmd.stdout(Stdio::from(log.reopen()?));
+            cmd.stderr(Stdio::from(log.reopen()?));
+            Some(log)
+        };
+        logging::command(&cmd);
+        let (child, group) =
+            interrupt::spawn(&mut cmd).context("Failed to start whisper-server")?;
+        let mut server = ServerProcess {
+            child,
+            _group: group,
+            model: opts.model.clone(),
+            base_url: base_url.clone(),
+            log,
+            logged: 0,
+            verbose: opts.verbose,
+        };
+
+        let ready = wait_until_healthy(&self.client, &mut server);
+        server.flush_log();
+        ready?;
+        if opts.verbose {
+            say!(
+                "whisper-server ready at {base_url} (model {})",
+                opts.model.display()
+            );
+        }
+        self.server = Some(server);
+        Ok(base_url)
+    }
+}
+
+impl Transcriber for WhisperServerBackend {
+    fn name(&self) -> &'static str {
+        "whisper-server"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            translate: true,
+            word_timestamps: true,
+            diarization: false,
+            decoding: true,
+        }
+    }
+
+    fn plan(
+        &self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        _detect_seconds: Option<u32>,
+    ) -> Result<Vec<String>> {
+        let mut plan = Vec::new();
+        let base_url = match &self.external_url {
+            Some(url) => url.clone(),
+            None => {
+                // started once for the batch, on a free port
+                plan.push(command_line(&self.server_command(opts, "PORT")?));
+                "http://127.0.0.1:PORT".to_string()
+            }
+        };
+        let fields: Vec<String> = decoding_fields(&opts.decoding)
+            .into_iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        plan.push(format!(
+            "POST {base_url}/inference file={} language={} translate={}{}",
+            wav.display(),
+            opts.language,
+            opts.translate,
+            fields.iter().map(|f| format!(" {f}")).collect::<String>()
+        ));
+        Ok(plan)
+    }
+
+    fn required_tools(&self) -> Vec<Tool> {
+        if self.external_url.is_some() {
+            Vec::new()
+        } else {
+            vec![Tool::WhisperServer]
+        }
+    }
+
+    fn uses_local_model(&self) -> bool {
+        // an external server has its own model loaded
+        self.external_url.is_none()
+    }
+
+    fn transcribe(
+        &mut self,
+        wav: &Path,
+        opts: &TranscribeOptions,
+        _progress: &Progress,
+    ) -> Result<Transcript> {
+        let base_url = self.ensure_server(opts)?;
+
+        let mut form = Form::new()
+            .file("file", wav)
+            .with_context(|| format!("Failed to read {}", wav.display()))?
+            .text("response_format", "verbose_json")
+            .text("language", opts.language.clone())
+            .text("translate", opts.translate.to_string());
+        if let Some(p) = &opts.prompt {
+            form = form.text("prompt", p.clone());
+        }
+        for (name, value) in decoding_fields(&opts.decoding) {
+            form = form.text(name, value);
+        }
+
+        let req = self
+            .client
+            .post(format!("{base_url}/inference"))
+            .multipart(form);
+        let reply = interrupt::interruptible(move || {
+            req.send()
+                .and_then(|r| r.error_for_status())
+                .and_then(|r| r.bytes())
+        });
+        if let Some(server) = &mut self.server {
+            server.flush_log();
+        }
+        let body = reply?.map_err(|e| {
+            request_error(e, format!("whisper-server request to {base_url} failed"))
+        })?;
+        Ok(verbose_json::parse(&body)?.into_transcript(opts.word_timestamps))
+    }
+}
+
+/// `/inference` form fields for the decoding params that are set.
+fn decoding_fields(d: &DecodingParams) -> Vec<(&'static str, String)> {
+    let mut fields = Vec::new();
+    let mut push = |name, value: Option<String>| {
+        if let Some(v) = value {
+            fields.push((name, v));
+        }
+    };
+    push("beam_size", d.beam_size.map(|v| v.to_string()));
+    push("best_of", d.best_of.map(|v| v.to_string()));
+    push("temperature", d.temperature.map(|v| v.to_string()));
+    push(
+        "temperature_inc",
+        d.effective_temperature_inc().map(|v| v.to_string()),
+    );
+    push("entropy_thold", d.entropy_threshold.map(|v| v.to_string()));
+    push("logprob_thold", d.logprob_threshold.map(|v| v.to_string()));
+    push(
+        "no_speech_thold",
+        d.no_speech_threshold.map(|v| v.to_string()),
+    );
+    push("max_len", d.max_len.map(|v| v.to_string()));
+    push("max_context", d.max_context.map(|v| v.to_string()));
+    if d.split_on_word {
+        push("split_on_word", Some("true".into()));
+    }
+    fields
+}
+
+fn free_port() -> Result<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0").context("No free local port")?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// Poll `/health` until the model is loaded. Older servers without `/health` only
+/// start listening once loaded, so any HTTP answer counts as ready.
+fn wait_until_healthy(client: &Client, server: &mut ServerProcess) -> Result<()> {
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    let url = format!("{}/health", server.base_url);
+    loop {
+        if let Some(status) = server.child.try_wait()? {
+            let tail = log_tail(server);
+            let oom = tail.iter().any(|l| is_oom_message(l));
+            let err = Vid2txtError::Transcription {
+                message: format!("whisper-server exited during startup ({status})"),
+                stderr: tail,
+            };
+            if oom {
+                return Err(anyhow::Error::new(OutOfMemory {
+                    backend: "whisper-server",
+                })
+                .context(err));
+            }
+            return Err(err.into());
+        }
+        if let Ok(resp) = client.get(&url).timeout(Duration::from_secs(5)).send()
+            && resp.status() != StatusCode::SERVICE_UNAVAILABLE
+        {
+            return Ok(());
+        }
+        if Instant::now() > deadline {
+            return Err(Vid2txtError::Transcription {
+                message: format!(
+                    "whisper-server did not become ready within {}s",
+                    STARTUP_TIMEOUT.as_secs()
+                ),
+                stderr: log_tail(server),
+            }
+            .into());
+        }
+        sleep(HEALTH_POLL_INTERVAL);
+    }
+}
+
+fn log_tail(server: &ServerProcess) -> Vec<String> {
+    let Some(log) = &server.log else {
+        return Vec::new();
+    };
+    let text = fs::read_to_string(log.path()).unwrap_or_default();
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..]
+        .iter()
+        .map(|l| l.to_string())
+        .collect()
+}
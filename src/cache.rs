@@ -0,0 +1,100 @@
+use crate::hf::{cache_dir_path, catalog_cache_info};
+use crate::models::format_size;
+use anyhow::{Context, Result};
+use std::fs;
+use std::time::Duration;
+use walkdir::WalkDir;
+
+/// `vid2txt cache status`: list every cached file with its size and age; model
+/// lists additionally report freshness against `ttl` and their stored validators.
+pub fn status(ttl: Duration) -> Result<()> {
+    let dir = cache_dir_path()?;
+    println!("Cache dir: {}", dir.display());
+    if !dir.exists() {
+        println!("(empty)");
+        return Ok(());
+    }
+
+    let mut total = 0u64;
+    let mut count = 0usize;
+    for entry in WalkDir::new(&dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+        let meta = entry.metadata()?;
+        let age = meta
+            .modified()
+            .ok()
+            .and_then(|m| m.elapsed().ok())
+            .unwrap_or(Duration::MAX);
+        total += meta.len();
+        count += 1;
+
+        let rel = path.strip_prefix(&dir).unwrap_or(path).display();
+        let name = entry.file_name().to_string_lossy();
+        let info = if name.starts_with("models-") && name.ends_with(".json") {
+            catalog_cache_info(path)
+        } else {
+            None
+        };
+        match info {
+            Some(info) => {
+                let state = if age < ttl { "fresh" } else { "stale" };
+                let validator = match (&info.etag, &info.last_modified) {
+                    (Some(etag), _) => format!("ETag {etag}"),
+                    (None, Some(lm)) => format!("Last-Modified {lm}"),
+                    (None, None) => "no validators".to_string(),
+                };
+                println!(
+                    "- {rel}: model list, {} models, {}, {state} (age {}), {validator}",
+                    info.models,
+                    format_size(meta.len()),
+                    format_age(age)
+                );
+            }
+            None => println!(
+                "- {rel}: {}, age {}",
+                format_size(meta.len()),
+                format_age(age)
+            ),
+        }
+    }
+
+    if count == 0 {
+        println!("(empty)");
+    } else {
+        println!("Total: {count} file(s), {}", format_size(total));
+    }
+    println!("Model list TTL: {}", format_age(ttl));
+    Ok(())
+}
+
+/// `vid2txt cache clear`: remove the whole vid2txt cache directory.
+pub fn clear() -> Result<()> {
+    let dir = cache_dir_path()?;
+    if !dir.exists() {
+        println!("Nothing to clear ({} does not exist)", dir.display());
+        return Ok(());
+    }
+    fs::remove_dir_all(&dir)
+        .with_context(|| format!("Failed to remove cache dir {}", dir.display()))?;
+    println!("🧹 Cleared cache: {}", dir.display());
+    Ok(())
+}
+
+pub fn format_age(d: Duration) -> String {
+    if d == Duration::MAX {
+        return "unknown".into();
+    }
+    let secs = d.as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
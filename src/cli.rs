@@ -1,42 +1,653 @@
-use clap::{Parser, ValueHint};
+use crate::backend::{ApiResponseFormat, BackendKind, DecodingParams, DecodingPreset};
+use crate::language::{parse_language, parse_language_or_auto};
+use clap::{CommandFactory, Parser, Subcommand, ValueHint};
+use std::ffi::OsString;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Parser)]
-#[command(name = "vid2txt", version, about)]
-pub struct Args {
-    /// Video URL
-    #[arg(value_hint = ValueHint::Url, required_unless_present = "list_models")]
-    pub url: Option<String>,
+#[command(
+    name = "vid2txt",
+    version,
+    about,
+    arg_required_else_help = true,
+    after_help = "Without a subcommand, `transcribe` is assumed: vid2txt <URL> -m base"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// Named [profile.<NAME>] from vid2txt.toml / ~/.config/vid2txt/config.toml to apply
+    #[arg(long, value_name = "NAME", env = "VID2TXT_PROFILE", global = true)]
+    pub profile: Option<String>,
+
+    /// How long the cached model list is used before revalidating it (e.g. 90m, 24h, 7d)
+    #[arg(long, value_name = "DURATION", env = "VID2TXT_CACHE_TTL", value_parser = parse_duration, global = true)]
+    pub cache_ttl: Option<Duration>,
+
+    #[command(flatten)]
+    pub tools: ToolArgs,
+}
+
+// Explicit paths of the external tools; bare names are looked up in PATH. A plain
+// comment: a doc comment on a flattened struct replaces the program's about text.
+#[derive(Debug, clap::Args)]
+pub struct ToolArgs {
+    /// ffmpeg binary (ffprobe is looked for next to it)
+    #[arg(long, value_name = "PATH", env = "VID2TXT_FFMPEG", global = true, help_heading = "Tools", value_hint = ValueHint::ExecutablePath)]
+    pub ffmpeg: Option<PathBuf>,
+
+    /// yt-dlp binary
+    #[arg(long, value_name = "PATH", env = "VID2TXT_YT_DLP", global = true, help_heading = "Tools", value_hint = ValueHint::ExecutablePath)]
+    pub yt_dlp: Option<PathBuf>,
+
+    /// whisper.cpp CLI binary, or a whisper.cpp build directory (where an old build's
+    /// main is also accepted); whisper-server is looked for next to it. Without it
+    /// whisper-cli and whisper-cpp are searched in PATH
+    #[arg(long, value_name = "PATH", env = "VID2TXT_WHISPER_CLI", global = true, help_heading = "Tools", value_hint = ValueHint::ExecutablePath)]
+    pub whisper_cli: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Download or extract the audio of each input and transcribe it (the default)
+    Transcribe(Box<TranscribeArgs>),
+    /// List the models available from the configured repos
+    Models(ModelsArgs),
+    /// Inspect or reset vid2txt's caches
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Check that the external tools and directories vid2txt needs are usable
+    Doctor(DoctorArgs),
+    /// Inspect the configuration files and profiles
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DoctorArgs {
+    /// Print the checks as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Print the settings `transcribe` would use here and where each one came from
+    Show,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheAction {
+    /// Show cached files, their age and whether they are still fresh
+    Status,
+    /// Delete everything vid2txt has cached
+    Clear,
+}
+
+/// Where the model catalog comes from; shared by `transcribe` and `models`.
+#[derive(Debug, clap::Args)]
+pub struct CatalogArgs {
+    /// Prefer quantized models first when listing/picking
+    #[arg(long, env = "VID2TXT_PREFER_QUANTIZED", value_parser = clap::builder::BoolishValueParser::new())]
+    pub prefer_quantized: bool,
+
+    /// Force refreshing the model list from Hugging Face, ignoring the cache TTL
+    #[arg(long, conflicts_with = "offline")]
+    pub refresh_models: bool,
+
+    /// Model repo to list/download from, as owner/name[@revision] or a full
+    /// <endpoint>/owner/name URL (repeatable; defaults to ggerganov/whisper.cpp)
+    #[arg(
+        long = "hf-repo",
+        value_name = "REPO",
+        env = "VID2TXT_HF_REPOS",
+        value_delimiter = ','
+    )]
+    pub hf_repos: Vec<String>,
+
+    /// Hugging Face compatible endpoint for repos given as owner/name
+    #[arg(long, value_name = "URL", env = "HF_ENDPOINT", default_value = crate::constants::HF_DEFAULT_ENDPOINT)]
+    pub hf_endpoint: String,
+
+    /// Bearer token for gated or private repos on huggingface.co; HOST=TOKEN sends a
+    /// token to that mirror host instead (repeatable)
+    #[arg(
+        long,
+        value_name = "[HOST=]TOKEN",
+        env = "HF_TOKEN",
+        hide_env_values = true,
+        value_delimiter = ','
+    )]
+    pub hf_token: Vec<String>,
+
+    /// Forbid all network access: cached model list and local models/files only, and
+    /// no --backend openai or --server-url
+    #[arg(long)]
+    pub offline: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ModelsArgs {
+    /// Only list models that are already downloaded
+    #[arg(long)]
+    pub downloaded: bool,
+
+    #[command(flatten)]
+    pub catalog: CatalogArgs,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct TranscribeArgs {
+    /// Video URL(s) or local media file(s); several inputs are processed as a batch
+    #[arg(value_name = "INPUT", value_hint = ValueHint::Url, required = true)]
+    pub inputs: Vec<String>,
 
     /// Output directory for WAV + transcript (.txt). Defaults to current dir
-    #[arg(short, long)]
+    #[arg(short, long, env = "VID2TXT_OUT")]
     pub out: Option<PathBuf>,
 
+    /// Output name inside --out, without extension; `/` makes subdirectories.
+    /// Placeholders: {title} {id} {uploader} {upload_date} {stem} {model} {lang} {date}
+    #[arg(long, value_name = "TEMPLATE", default_value = crate::constants::DEFAULT_OUTPUT_TEMPLATE)]
+    pub output_template: String,
+
     /// Whisper model alias (e.g., large-v3) OR an existing file name/path
-    #[arg(short, long)]
+    #[arg(short, long, env = "VID2TXT_MODEL")]
     pub model: Option<String>,
 
+    /// Model used when no --model is given and the picker can't be shown (no TTY)
+    /// or is skipped with --yes; "auto" for the hardware recommendation
+    #[arg(long, value_name = "MODEL", env = "VID2TXT_DEFAULT_MODEL")]
+    pub default_model: Option<String>,
+
+    /// Don't prompt: accept the default model (--default-model, else the recommendation)
+    #[arg(short, long)]
+    pub yes: bool,
+
     /// Force language code for transcription (e.g. en, pt, es)
-    #[arg(long, default_value = "auto")]
+    #[arg(
+        long,
+        env = "VID2TXT_LANGUAGE",
+        default_value = "auto",
+        value_parser = parse_language_or_auto
+    )]
     pub language: String,
 
-    /// Number of threads for whisper-cli (-t)
+    /// With --language auto: seconds of audio used to detect the language first
+    #[arg(long, value_name = "SECS", default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..=600))]
+    pub detect_seconds: u32,
+
+    /// Reject inputs whose detected language is not in this list (e.g. en,de)
+    #[arg(long, value_name = "LANGS", value_delimiter = ',', value_parser = parse_language)]
+    pub allowed_languages: Vec<String>,
+
+    /// When English is detected, switch to the model's .en variant if it is already downloaded
     #[arg(long)]
+    pub english_model: bool,
+
+    /// Number of threads for whisper-cli (-t)
+    #[arg(long, env = "VID2TXT_THREADS")]
     pub threads: Option<u32>,
 
-    /// Show command output from yt-dlp/whisper-cli
-    #[arg(short, long)]
-    pub verbose: bool,
+    /// Transcription engine
+    #[arg(long, value_enum, default_value_t = BackendKind::WhisperCli)]
+    pub backend: BackendKind,
+
+    /// Use an already running whisper-server instead of spawning one (whisper-server backend)
+    #[arg(long, value_name = "URL", env = "VID2TXT_SERVER_URL")]
+    pub server_url: Option<String>,
+
+    /// Base URL of an OpenAI-compatible transcription API, e.g. http://gpu-box:8000/v1 (openai backend)
+    #[arg(long, value_name = "URL", env = "VID2TXT_API_BASE_URL")]
+    pub api_base_url: Option<String>,
+
+    /// API key sent as a bearer token (openai backend)
+    #[arg(
+        long,
+        value_name = "KEY",
+        env = "VID2TXT_API_KEY",
+        hide_env_values = true
+    )]
+    pub api_key: Option<String>,
+
+    /// Model name requested from the API (openai backend)
+    #[arg(long, value_name = "NAME", default_value = "whisper-1")]
+    pub api_model: String,
+
+    /// Response format requested from the API (openai backend)
+    #[arg(long, value_enum, default_value_t = ApiResponseFormat::VerboseJson)]
+    pub api_response_format: ApiResponseFormat,
+
+    /// Initial prompt to bias recognition (style, names, jargon)
+    #[arg(long, value_name = "TEXT", conflicts_with = "prompt_file")]
+    pub prompt: Option<String>,
+
+    /// Read the initial prompt from a file
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub prompt_file: Option<PathBuf>,
+
+    /// Terms (one per line, optional "= alias, alias") added to the prompt and
+    /// used to fix their spelling in the transcript
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub glossary: Option<PathBuf>,
+
+    /// Don't retry with another model when whisper runs out of memory (or confidence is low)
+    #[arg(long, help_heading = "Model fallback")]
+    pub no_model_fallback: bool,
+
+    /// Retry with the next larger model when the mean token probability is below this (0.0-1.0)
+    #[arg(long, value_name = "P", help_heading = "Model fallback", value_parser = parse_unit_f32)]
+    pub min_confidence: Option<f32>,
+
+    /// Maximum number of models tried per input
+    #[arg(long, value_name = "N", default_value_t = 3, help_heading = "Model fallback", value_parser = clap::value_parser!(u32).range(1..=10))]
+    pub max_attempts: u32,
 
-    /// List available remote Whisper models and exit
+    /// Keep the transcript exactly as the backend returned it (no loop or
+    /// hallucination filtering)
+    #[arg(long, help_heading = "Filtering")]
+    pub no_filter: bool,
+
+    /// Back-to-back repeats of a sentence (or segment) treated as a loop and collapsed;
+    /// repeats inside a sentence also need to span 10 words
+    #[arg(long, value_name = "N", default_value_t = 3, help_heading = "Filtering", value_parser = clap::value_parser!(u32).range(2..))]
+    pub max_repeats: u32,
+
+    /// Drop segments the backend rates at least this likely to be non-speech (0.0-1.0)
+    #[arg(long, value_name = "P", default_value_t = 0.6, help_heading = "Filtering", value_parser = parse_unit_f32)]
+    pub drop_no_speech: f32,
+
+    /// File of phrases (one per line) removed when they form a whole segment;
+    /// replaces the built-in list of typical outro hallucinations
+    #[arg(long, value_name = "FILE", help_heading = "Filtering", value_hint = ValueHint::FilePath)]
+    pub hallucination_phrases: Option<PathBuf>,
+
+    /// Decoding preset; the individual decoding flags below override it
+    #[arg(long, value_enum, help_heading = "Decoding")]
+    pub preset: Option<DecodingPreset>,
+
+    /// Beam width for beam search (1 = greedy)
+    #[arg(long, value_name = "N", help_heading = "Decoding", value_parser = clap::value_parser!(u32).range(1..=16))]
+    pub beam_size: Option<u32>,
+
+    /// Number of candidates when sampling with temperature > 0
+    #[arg(long, value_name = "N", help_heading = "Decoding", value_parser = clap::value_parser!(u32).range(1..=16))]
+    pub best_of: Option<u32>,
+
+    /// Initial sampling temperature (0.0-1.0)
+    #[arg(long, value_name = "T", help_heading = "Decoding", value_parser = parse_unit_f32)]
+    pub temperature: Option<f32>,
+
+    /// Temperature step for retrying segments that fail the thresholds (0.0-1.0)
+    #[arg(long, value_name = "T", help_heading = "Decoding", value_parser = parse_unit_f32, conflicts_with = "no_fallback")]
+    pub temperature_inc: Option<f32>,
+
+    /// Never retry segments at a higher temperature
+    #[arg(long, help_heading = "Decoding")]
+    pub no_fallback: bool,
+
+    /// Entropy threshold above which a segment is decoded again (> 0)
+    #[arg(long, value_name = "X", help_heading = "Decoding", value_parser = parse_positive_f32)]
+    pub entropy_threshold: Option<f32>,
+
+    /// Average log probability below which a segment is decoded again (<= 0)
+    #[arg(long, value_name = "X", help_heading = "Decoding", value_parser = parse_logprob, allow_negative_numbers = true)]
+    pub logprob_threshold: Option<f32>,
+
+    /// Probability above which a window counts as silence (0.0-1.0)
+    #[arg(long, value_name = "P", help_heading = "Decoding", value_parser = parse_unit_f32)]
+    pub no_speech_threshold: Option<f32>,
+
+    /// Maximum segment length in characters
+    #[arg(long, value_name = "CHARS", help_heading = "Decoding", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_len: Option<u32>,
+
+    /// Split long segments on words instead of tokens
+    #[arg(long, help_heading = "Decoding", requires = "max_len")]
+    pub split_on_word: bool,
+
+    /// Maximum number of text context tokens kept between windows (0 = none)
+    #[arg(long, value_name = "TOKENS", help_heading = "Decoding")]
+    pub max_context: Option<u32>,
+
+    /// Number of processors to split the audio across (whisper-cli/whisper-server)
+    #[arg(long, value_name = "N", help_heading = "Decoding", value_parser = clap::value_parser!(u32).range(1..=16))]
+    pub processors: Option<u32>,
+
+    /// Translate the speech to English instead of transcribing it
     #[arg(long)]
-    pub list_models: bool,
+    pub translate: bool,
 
-    /// Prefer quantized models first when listing/picking
+    /// Also write per-word timings to a .json transcript
     #[arg(long)]
-    pub prefer_quantized: bool,
+    pub word_timestamps: bool,
 
-    /// Force refreshing the model list from Hugging Face, ignoring cache
+    /// Label speakers in the transcript (whisper-cli only). Audio is then extracted in
+    /// stereo and speakers are told apart by channel, so it needs a stereo recording
+    /// with one speaker per side
     #[arg(long)]
-    pub refresh_models: bool,
+    pub diarize: bool,
+
+    /// Print one JSON object per input on stdout (status, paths, model, language,
+    /// timings, error); all other output goes to stderr
+    #[arg(long)]
+    pub json: bool,
+
+    /// Like --json, plus NDJSON progress events (stage_started, progress,
+    /// stage_finished, input_started, input_finished)
+    #[arg(long, conflicts_with = "json")]
+    pub json_events: bool,
+
+    /// Print how each input would be processed (input type, model, output paths,
+    /// exact tool command lines) without downloading or writing anything
+    #[arg(long, conflicts_with_all = ["json", "json_events"])]
+    pub dry_run: bool,
+
+    /// Show command output from yt-dlp/whisper-cli; -vv also prints each command
+    /// line, and timestamps every line
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only print warnings and errors (no progress bars)
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Write a timestamped log with every command line and the full output of each
+    /// tool; in a batch each input also gets its own FILE.N log
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, conflicts_with = "dry_run")]
+    pub log_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub catalog: CatalogArgs,
+}
+
+impl TranscribeArgs {
+    /// `--preset` (if any) with the individual decoding flags applied on top.
+    pub fn decoding(&self) -> DecodingParams {
+        let mut d = self.preset.map(DecodingPreset::params).unwrap_or_default();
+        d.beam_size = self.beam_size.or(d.beam_size);
+        d.best_of = self.best_of.or(d.best_of);
+        d.temperature = self.temperature.or(d.temperature);
+        if self.no_fallback {
+            d.no_fallback = true;
+            d.temperature_inc = None;
+        } else if let Some(inc) = self.temperature_inc {
+            d.no_fallback = false;
+            d.temperature_inc = Some(inc);
+        }
+        d.entropy_threshold = self.entropy_threshold.or(d.entropy_threshold);
+        d.logprob_threshold = self.logprob_threshold.or(d.logprob_threshold);
+        d.no_speech_threshold = self.no_speech_threshold.or(d.no_speech_threshold);
+        d.max_len = self.max_len.or(d.max_len);
+        d.split_on_word |= self.split_on_word;
+        d.max_context = self.max_context.or(d.max_context);
+        d.processors = self.processors.or(d.processors);
+        d
+    }
+}
+
+/// Insert `transcribe` when the command line names no subcommand, so that
+/// `vid2txt <URL> -m base` keeps working. The old `--list-models` flag maps to `models`.
+pub fn with_default_subcommand(mut args: Vec<OsString>) -> Vec<OsString> {
+    let cmd = Cli::command();
+    // global options may come before the subcommand
+    let globals: Vec<String> = cmd
+        .get_arguments()
+        .filter(|a| a.is_global_set())
+        .filter_map(|a| a.get_long())
+        .map(|l| format!("--{l}"))
+        .collect();
+    let mut i = 1;
+    while let Some(a) = args.get(i).map(|a| a.to_string_lossy()) {
+        if globals.iter().any(|g| *g == a) {
+            i += 2;
+        } else if globals.iter().any(|g| a.starts_with(&format!("{g}="))) {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+
+    let Some(first) = args.get(i).map(|a| a.to_string_lossy().to_string()) else {
+        return args;
+    };
+    let named = matches!(
+        first.as_str(),
+        "-h" | "--help" | "-V" | "--version" | "help"
+    ) || cmd.find_subcommand(&first).is_some();
+    if named {
+        return args;
+    }
+    if let Some(at) = args.iter().position(|a| a == "--list-models") {
+        eprintln!("ℹ️  --list-models is now `vid2txt models`");
+        args.remove(at);
+        args.insert(i.min(args.len()), "models".into());
+    } else {
+        args.insert(i.min(args.len()), "transcribe".into());
+    }
+    args
+}
+
+/// Parse `<n>[s|m|h|d]` (a bare number means seconds).
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (num, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let n: u64 = num
+        .parse()
+        .map_err(|_| format!("invalid duration '{s}' (expected e.g. 90m, 24h, 7d)"))?;
+    let secs = match unit {
+        "s" => n,
+        "m" => n * 60,
+        "h" => n * 60 * 60,
+        "d" => n * 24 * 60 * 60,
+        _ => return Err(format!("unknown duration unit '{unit}' (use s, m, h or d)")),
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+fn parse_f32(s: &str) -> Result<f32, String> {
+    s.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| format!("'{s}' is not a number"))
+}
+
+fn parse_unit_f32(s: &str) -> Result<f32, String> {
+    let v = parse_f32(s)?;
+    if (0.0..=1.0).contains(&v) {
+        Ok(v)
+    } else {
+        Err(format!("{v} is not between 0.0 and 1.0"))
+    }
+}
+
+fn parse_positive_f32(s: &str) -> Result<f32, String> {
+    let v = parse_f32(s)?;
+    if v > 0.0 {
+        Ok(v)
+    } else {
+        Err(format!("{v} must be greater than 0"))
+    }
+}
+
+fn parse_logprob(s: &str) -> Result<f32, String> {
+    let v = parse_f32(s)?;
+    if v <= 0.0 {
+        Ok(v)
+    } else {
+        Err(format!("{v} must be 0 or negative (a log probability)"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::FromArgMatches;
+
+    fn with_default(args: &[&str]) -> Vec<String> {
+        let args = std::iter::once("vid2txt")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect();
+        with_default_subcommand(args)
+            .into_iter()
+            .skip(1)
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn defaults_to_transcribe() {
+        assert_eq!(
+            with_default(&["a.mp4", "-m", "base"]),
+            ["transcribe", "a.mp4", "-m", "base"]
+        );
+        assert_eq!(
+            with_default(&["--profile", "fast", "--ffmpeg=/x/ffmpeg", "a.mp4"]),
+            [
+                "--profile",
+                "fast",
+                "--ffmpeg=/x/ffmpeg",
+                "transcribe",
+                "a.mp4"
+            ]
+        );
+        assert_eq!(
+            with_default(&["-m", "base", "x"]),
+            ["transcribe", "-m", "base", "x"]
+        );
+    }
+
+    #[test]
+    fn keeps_named_subcommands() {
+        for args in [
+            &["models"][..],
+            &["--profile", "p", "doctor", "--json"],
+            &["cache", "clear"],
+            &["--help"],
+            &["-V"],
+            &["help", "models"],
+            &[],
+        ] {
+            assert_eq!(with_default(args), args, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn maps_list_models() {
+        assert_eq!(
+            with_default(&["--list-models", "--prefer-quantized"]),
+            ["models", "--prefer-quantized"]
+        );
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 90m "), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("24h"), Ok(Duration::from_secs(86_400)));
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(604_800)));
+        assert_eq!(parse_duration("0s"), Ok(Duration::ZERO));
+        for bad in ["", "m", "1.5h", "-1h", "3w", "10 m"] {
+            assert!(parse_duration(bad).is_err(), "{bad}");
+        }
+    }
+
+    fn transcribe(args: &[&str]) -> Result<TranscribeArgs, clap::Error> {
+        let args = ["vid2txt", "transcribe", "a.mp4"].iter().chain(args);
+        let matches = Cli::command().try_get_matches_from(args)?;
+        match Cli::from_arg_matches(&matches)?.command {
+            Command::Transcribe(args) => Ok(*args),
+            _ => panic!("expected transcribe"),
+        }
+    }
+
+    fn whisper_args(args: &[&str]) -> String {
+        transcribe(args)
+            .unwrap()
+            .decoding()
+            .whisper_cpp_args()
+            .join(" ")
+    }
+
+    #[test]
+    fn decoding_flags_override_the_preset() {
+        assert_eq!(whisper_args(&[]), "");
+        assert_eq!(whisper_args(&["--preset", "fast"]), "-bs 1 -bo 1 -nf");
+        assert_eq!(
+            whisper_args(&[
+                "--preset",
+                "balanced",
+                "--beam-size",
+                "3",
+                "--max-len",
+                "40",
+                "--split-on-word"
+            ]),
+            "-bs 3 -bo 5 -tp 0 -tpi 0.2 -ml 40 -sow"
+        );
+        assert_eq!(
+            whisper_args(&[
+                "--preset",
+                "accurate",
+                "--temperature",
+                "0.4",
+                "--processors",
+                "2"
+            ]),
+            "-bs 8 -bo 8 -tp 0.4 -tpi 0.2 -et 2.4 -lpt -1 -p 2"
+        );
+        assert_eq!(whisper_args(&["--no-fallback"]), "-nf");
+    }
+
+    #[test]
+    fn fallback_flags_replace_each_other() {
+        // a step turns the fast preset's fallback back on
+        let d = transcribe(&["--preset", "fast", "--temperature-inc", "0.4"])
+            .unwrap()
+            .decoding();
+        assert!(!d.no_fallback);
+        assert_eq!(d.whisper_cpp_args().join(" "), "-bs 1 -bo 1 -tpi 0.4");
+        // and --no-fallback drops the preset's step
+        let d = transcribe(&["--preset", "accurate", "--no-fallback"])
+            .unwrap()
+            .decoding();
+        assert_eq!(d.temperature_inc, None);
+        assert_eq!(d.effective_temperature_inc(), Some(0.0));
+        assert!(d.whisper_cpp_args().ends_with(&["-nf".to_string()]));
+        assert!(transcribe(&["--no-fallback", "--temperature-inc", "0.2"]).is_err());
+    }
+
+    #[test]
+    fn command_line_is_consistent() {
+        Cli::command().debug_assert();
+        let matches = Cli::command().get_matches_from(with_default_subcommand(
+            [
+                "vid2txt",
+                "a.mp4",
+                "--hf-token",
+                "t1,mirror.example=t2",
+                "--cache-ttl",
+                "1h",
+            ]
+            .map(OsString::from)
+            .to_vec(),
+        ));
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        assert_eq!(cli.cache_ttl, Some(Duration::from_secs(3600)));
+        let Command::Transcribe(args) = cli.command else {
+            panic!("expected transcribe");
+        };
+        assert_eq!(args.inputs, ["a.mp4"]);
+        assert_eq!(args.catalog.hf_token, ["t1", "mirror.example=t2"]);
+    }
 }
@@ -0,0 +1,551 @@
+//! Defaults from config files, layered under the command line:
+//! CLI > env > project `vid2txt.toml` > user `~/.config/vid2txt/config.toml` > built-in.
+//!
+//! Both files hold top-level settings plus optional `[profile.<name>]` tables that
+//! `--profile <name>` lays over the top-level ones of the same file.
+
+use crate::backend::{BackendKind, DecodingPreset};
+use crate::cli::{Cli, Command, ConfigAction, TranscribeArgs, with_default_subcommand};
+use crate::language::parse_language_or_auto;
+use anyhow::{Context, Result, anyhow};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, ValueEnum};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const PROJECT_FILE: &str = "vid2txt.toml";
+
+/// Settings a config file (or profile) may set; the keys match the long flags.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    pub out: Option<PathBuf>,
+    pub output_template: Option<String>,
+    pub model: Option<String>,
+    pub language: Option<String>,
+    pub threads: Option<u32>,
+    pub prefer_quantized: Option<bool>,
+    pub backend: Option<BackendKind>,
+    pub preset: Option<DecodingPreset>,
+    pub word_timestamps: Option<bool>,
+    pub glossary: Option<PathBuf>,
+    pub server_url: Option<String>,
+    pub api_base_url: Option<String>,
+    pub ffmpeg: Option<PathBuf>,
+    pub yt_dlp: Option<PathBuf>,
+    pub whisper_cli: Option<PathBuf>,
+}
+
+impl Settings {
+    /// Apply what clap's value parsers would have: paths become relative to the
+    /// config file (tools given by bare name stay PATH lookups), languages are
+    /// normalized to whisper codes.
+    fn normalize(&mut self, file: &Path) -> Result<()> {
+        let dir = file.parent().unwrap_or(Path::new("."));
+        let tools = [&mut self.ffmpeg, &mut self.yt_dlp, &mut self.whisper_cli]
+            .into_iter()
+            .flatten()
+            .filter(|p| p.components().count() > 1);
+        for p in [&mut self.out, &mut self.glossary]
+            .into_iter()
+            .flatten()
+            .chain(tools)
+        {
+            if p.is_relative() {
+                *p = dir.join(&*p);
+            }
+        }
+        if let Some(l) = &mut self.language {
+            *l = parse_language_or_auto(l)
+                .map_err(|e| anyhow!("{}: language: {e}", file.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a resolved setting came from.
+#[derive(Debug, Clone)]
+pub enum Source {
+    BuiltIn,
+    File(PathBuf),
+    Profile(PathBuf, String),
+    Env(String),
+    Cli,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::BuiltIn => f.write_str("built-in"),
+            Source::File(p) => write!(f, "{}", p.display()),
+            Source::Profile(p, name) => write!(f, "{} [profile.{name}]", p.display()),
+            Source::Env(var) => write!(f, "env {var}"),
+            Source::Cli => f.write_str("command line"),
+        }
+    }
+}
+
+/// One config file, parsed.
+struct ConfigFile {
+    path: PathBuf,
+    base: Settings,
+    profiles: toml::Table,
+}
+
+impl ConfigFile {
+    fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        let mut table: toml::Table =
+            toml::from_str(&text).with_context(|| format!("Invalid config {}", path.display()))?;
+        let profiles = match table.remove("profile") {
+            Some(toml::Value::Table(t)) => t,
+            Some(_) => return Err(anyhow!("{}: `profile` must be a table", path.display())),
+            None => toml::Table::new(),
+        };
+        let mut base: Settings = table
+            .try_into()
+            .with_context(|| format!("Invalid config {}", path.display()))?;
+        base.normalize(path)?;
+        Ok(Some(Self {
+            path: path.to_path_buf(),
+            base,
+            profiles,
+        }))
+    }
+
+    fn profile(&self, name: &str) -> Result<Option<Settings>> {
+        let Some(value) = self.profiles.get(name) else {
+            return Ok(None);
+        };
+        let mut s: Settings = value
+            .clone()
+            .try_into()
+            .with_context(|| format!("Invalid [profile.{name}] in {}", self.path.display()))?;
+        s.normalize(&self.path)?;
+        Ok(Some(s))
+    }
+}
+
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("vid2txt").join("config.toml"))
+}
+
+/// The nearest `vid2txt.toml` in the current directory or its parents.
+pub fn project_config_path() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|d| d.join(PROJECT_FILE))
+        .find(|p| p.is_file())
+}
+
+/// Resolved settings with their origin, for `vid2txt config show`.
+pub struct Resolved {
+    pub files: Vec<PathBuf>,
+    /// Config problems that were skipped over (only for commands that diagnose them)
+    pub errors: Vec<String>,
+    pub profile: Option<String>,
+    pub settings: Vec<(&'static str, String, Source)>,
+}
+
+/// Parse the command line and fill everything it (and the environment) left at the
+/// built-in default from the config files. A broken config only stops `transcribe`
+/// and `models`; `doctor` and `config show` report it instead.
+pub fn load_args() -> Result<(Cli, Resolved)> {
+    let matches =
+        Cli::command().get_matches_from(with_default_subcommand(std::env::args_os().collect()));
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let strict = matches!(cli.command, Command::Transcribe(_) | Command::Models(_));
+
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    for path in [user_config_path(), project_config_path()]
+        .into_iter()
+        .flatten()
+    {
+        match ConfigFile::load(&path) {
+            Ok(file) => files.extend(file),
+            Err(e) if !strict => errors.push(format!("{e:#}")),
+            Err(e) => return Err(e),
+        }
+    }
+    let layers = match layers(&files, cli.profile.as_deref()) {
+        Ok(layers) => layers,
+        Err(e) if !strict => {
+            errors.push(format!("{e:#}"));
+            layers(&files, None)?
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut settings = match (&mut cli.command, matches.subcommand()) {
+        (Command::Transcribe(args), Some((_, sub))) => resolve_transcribe(sub, &layers, args),
+        (Command::Models(args), Some((_, sub))) => {
+            let mut r = Resolver::new(sub, &layers);
+            r.apply(
+                "prefer_quantized",
+                &mut args.catalog.prefer_quantized,
+                |s| s.prefer_quantized,
+            );
+            r.out
+        }
+        (
+            Command::Config {
+                action: ConfigAction::Show,
+            },
+            _,
+        ) => {
+            // what a plain `vid2txt transcribe` would use here
+            let cmd = TranscribeArgs::augment_args(clap::Command::new("transcribe"))
+                .mut_arg("inputs", |a| a.required(false));
+            let sub = cmd.get_matches_from(["transcribe"]);
+            let mut args = TranscribeArgs::from_arg_matches(&sub).unwrap_or_else(|e| e.exit());
+            resolve_transcribe(&sub, &layers, &mut args)
+        }
+        _ => Vec::new(),
+    };
+    let mut r = Resolver::new(&matches, &layers);
+    r.apply("ffmpeg", &mut cli.tools.ffmpeg, |s| {
+        s.ffmpeg.clone().map(Some)
+    });
+    r.apply("yt_dlp", &mut cli.tools.yt_dlp, |s| {
+        s.yt_dlp.clone().map(Some)
+    });
+    r.apply("whisper_cli", &mut cli.tools.whisper_cli, |s| {
+        s.whisper_cli.clone().map(Some)
+    });
+    settings.extend(r.out);
+
+    let resolved = Resolved {
+        files: files.into_iter().map(|f| f.path).collect(),
+        errors,
+        profile: cli.profile.clone(),
+        settings,
+    };
+    Ok((cli, resolved))
+}
+
+/// Each file's top-level settings, then its `[profile.<name>]`; lowest precedence first.
+fn layers(files: &[ConfigFile], profile: Option<&str>) -> Result<Vec<(Settings, Source)>> {
+    let mut layers: Vec<(Settings, Source)> = Vec::new();
+    for f in files {
+        layers.push((f.base.clone(), Source::File(f.path.clone())));
+        if let Some(name) = profile
+            && let Some(p) = f.profile(name)?
+        {
+            layers.push((p, Source::Profile(f.path.clone(), name.to_string())));
+        }
+    }
+    if let Some(name) = profile
+        && !layers.iter().any(|(_, s)| matches!(s, Source::Profile(..)))
+    {
+        let mut known: Vec<&str> = files
+            .iter()
+            .flat_map(|f| f.profiles.keys().map(String::as_str))
+            .collect();
+        known.sort_unstable();
+        known.dedup();
+        return Err(anyhow!(
+            "Unknown profile '{name}' (defined: {})",
+            if known.is_empty() {
+                "none".to_string()
+            } else {
+                known.join(", ")
+            }
+        ));
+    }
+    Ok(layers)
+}
+
+fn resolve_transcribe(
+    matches: &ArgMatches,
+    layers: &[(Settings, Source)],
+    args: &mut TranscribeArgs,
+) -> Vec<(&'static str, String, Source)> {
+    let mut r = Resolver::new(matches, layers);
+    r.apply("out", &mut args.out, |s| s.out.clone().map(Some));
+    r.apply("output_template", &mut args.output_template, |s| {
+        s.output_template.clone()
+    });
+    r.apply("model", &mut args.model, |s| s.model.clone().map(Some));
+    r.apply("language", &mut args.language, |s| s.language.clone());
+    r.apply("threads", &mut args.threads, |s| s.threads.map(Some));
+    r.apply(
+        "prefer_quantized",
+        &mut args.catalog.prefer_quantized,
+        |s| s.prefer_quantized,
+    );
+    r.apply("backend", &mut args.backend, |s| s.backend);
+    r.apply("preset", &mut args.preset, |s| s.preset.map(Some));
+    r.apply("word_timestamps", &mut args.word_timestamps, |s| {
+        s.word_timestamps
+    });
+    r.apply("glossary", &mut args.glossary, |s| {
+        s.glossary.clone().map(Some)
+    });
+    r.apply("server_url", &mut args.server_url, |s| {
+        s.server_url.clone().map(Some)
+    });
+    r.apply("api_base_url", &mut args.api_base_url, |s| {
+        s.api_base_url.clone().map(Some)
+    });
+    r.out
+}
+
+struct Resolver<'a> {
+    matches: &'a ArgMatches,
+    layers: &'a [(Settings, Source)],
+    out: Vec<(&'static str, String, Source)>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(matches: &'a ArgMatches, layers: &'a [(Settings, Source)]) -> Self {
+        Self {
+            matches,
+            layers,
+            out: Vec::new(),
+        }
+    }
+
+    /// Keep `field` if the CLI or env set it, else take the highest-precedence layer's value.
+    fn apply<T: ShowValue>(
+        &mut self,
+        id: &'static str,
+        field: &mut T,
+        pick: impl Fn(&Settings) -> Option<T>,
+    ) {
+        let source = match self.matches.value_source(id) {
+            Some(ValueSource::CommandLine) => Source::Cli,
+            Some(ValueSource::EnvVariable) => Source::Env(env_name(id)),
+            _ => match self
+                .layers
+                .iter()
+                .rev()
+                .find_map(|(s, src)| pick(s).map(|v| (v, src)))
+            {
+                Some((v, src)) => {
+                    *field = v;
+                    src.clone()
+                }
+                None => Source::BuiltIn,
+            },
+        };
+        self.out.push((id, field.show(), source));
+    }
+}
+
+fn env_name(id: &str) -> String {
+    let cmd = Cli::command();
+    std::iter::once(&cmd)
+        .chain(cmd.get_subcommands())
+        .flat_map(|c| c.get_arguments())
+        .find(|a| a.get_id() == id)
+        .and_then(|a| a.get_env())
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// How `config show` prints a value.
+trait ShowValue {
+    fn show(&self) -> String;
+}
+
+impl ShowValue for String {
+    fn show(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ShowValue for PathBuf {
+    fn show(&self) -> String {
+        self.display().to_string()
+    }
+}
+
+impl ShowValue for u32 {
+    fn show(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ShowValue for bool {
+    fn show(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ShowValue for BackendKind {
+    fn show(&self) -> String {
+        value_name(self)
+    }
+}
+
+impl ShowValue for DecodingPreset {
+    fn show(&self) -> String {
+        value_name(self)
+    }
+}
+
+impl<T: ShowValue> ShowValue for Option<T> {
+    fn show(&self) -> String {
+        self.as_ref()
+            .map(ShowValue::show)
+            .unwrap_or_else(|| "(unset)".into())
+    }
+}
+
+fn value_name(v: &impl ValueEnum) -> String {
+    v.to_possible_value()
+        .map(|p| p.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// `vid2txt config show`
+pub fn show(resolved: &Resolved) -> Result<()> {
+    let state = |p: &Path| {
+        if resolved.files.iter().any(|f| f == p) {
+            ""
+        } else if p.is_file() {
+            " (invalid, ignored)"
+        } else {
+            " (not found)"
+        }
+    };
+    println!("Config files (lowest precedence first):");
+    match user_config_path() {
+        Some(p) => println!("- user:    {}{}", p.display(), state(&p)),
+        None => println!("- user:    (no config dir on this platform)"),
+    }
+    match project_config_path() {
+        Some(p) => println!("- project: {}{}", p.display(), state(&p)),
+        None => println!("- project: no {PROJECT_FILE} here or in a parent directory"),
+    }
+    if let Some(p) = &resolved.profile {
+        println!("Profile: {p}");
+    }
+    println!();
+    let width = resolved
+        .settings
+        .iter()
+        .map(|(k, ..)| k.len())
+        .max()
+        .unwrap_or(0);
+    for (key, value, source) in &resolved.settings {
+        let key = key.replace('_', "-");
+        println!("{key:width$}  {value}  ({source})");
+    }
+    if resolved.errors.is_empty() {
+        return Ok(());
+    }
+    println!();
+    for e in &resolved.errors {
+        println!("❌ {e}");
+    }
+    Err(anyhow!("{} config error(s)", resolved.errors.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(dir: &Path, name: &str, text: &str) -> ConfigFile {
+        let path = dir.join(name);
+        fs::write(&path, text).unwrap();
+        ConfigFile::load(&path).unwrap().unwrap()
+    }
+
+    /// `transcribe` with `argv`, resolved against `files`.
+    fn resolve(
+        files: &[ConfigFile],
+        profile: Option<&str>,
+        argv: &[&str],
+    ) -> (TranscribeArgs, Vec<(&'static str, String, Source)>) {
+        let matches =
+            Cli::command().get_matches_from(["vid2txt", "transcribe"].iter().chain(argv).copied());
+        let (_, sub) = matches.subcommand().unwrap();
+        let mut args = TranscribeArgs::from_arg_matches(sub).unwrap();
+        let layers = layers(files, profile).unwrap();
+        let out = resolve_transcribe(sub, &layers, &mut args);
+        (args, out)
+    }
+
+    fn source<'a>(out: &'a [(&'static str, String, Source)], key: &str) -> &'a Source {
+        &out.iter().find(|(k, ..)| *k == key).unwrap().2
+    }
+
+    #[test]
+    fn cli_beats_env_beats_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = write_config(
+            dir.path(),
+            "user.toml",
+            "model = \"base\"\nlanguage = \"german\"\nthreads = 2\nout = \"subs\"\n",
+        );
+        let project = write_config(
+            dir.path(),
+            "vid2txt.toml",
+            "model = \"small\"\n[profile.fast]\nthreads = 8\nmodel = \"tiny\"\n",
+        );
+        let files = [user, project];
+
+        let (args, out) = resolve(&files, None, &["x.mp4", "--language", "fr"]);
+        assert_eq!(args.language, "fr");
+        assert!(matches!(source(&out, "language"), Source::Cli));
+        assert_eq!(args.model.as_deref(), Some("small"));
+        assert!(matches!(source(&out, "model"), Source::File(p) if p.ends_with("vid2txt.toml")));
+        assert_eq!(args.threads, Some(2));
+        // relative to the file that set it
+        assert_eq!(args.out, Some(dir.path().join("subs")));
+        assert!(matches!(source(&out, "backend"), Source::BuiltIn));
+
+        let (args, out) = resolve(&files, Some("fast"), &["x.mp4", "-m", "large"]);
+        assert_eq!(args.model.as_deref(), Some("large"));
+        assert_eq!(args.threads, Some(8));
+        assert!(matches!(source(&out, "threads"), Source::Profile(_, name) if name == "fast"));
+        // languages are normalized like --language
+        assert_eq!(args.language, "de");
+
+        // the environment beats every file, the command line beats the environment
+        // SAFETY: std synchronizes its own environment access, and no test reads
+        // these variables through libc
+        unsafe {
+            std::env::set_var("VID2TXT_THREADS", "4");
+            std::env::set_var("VID2TXT_MODEL", "medium");
+        }
+        let resolved = resolve(&files, Some("fast"), &["x.mp4", "-m", "large"]);
+        unsafe {
+            std::env::remove_var("VID2TXT_THREADS");
+            std::env::remove_var("VID2TXT_MODEL");
+        }
+        let (args, out) = resolved;
+        assert_eq!(args.threads, Some(4));
+        assert!(matches!(source(&out, "threads"), Source::Env(var) if var == "VID2TXT_THREADS"));
+        assert_eq!(args.model.as_deref(), Some("large"));
+        assert!(matches!(source(&out, "model"), Source::Cli));
+        assert_eq!(args.out, Some(dir.path().join("subs")));
+    }
+
+    #[test]
+    fn unknown_profiles_and_keys_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = write_config(dir.path(), "a.toml", "[profile.fast]\nthreads = 8\n");
+        let err = layers(&[file], Some("slow")).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown profile 'slow' (defined: fast)");
+
+        for text in ["modle = \"base\"", "language = \"klingon\"", "profile = 1"] {
+            let path = dir.path().join("bad.toml");
+            fs::write(&path, text).unwrap();
+            assert!(ConfigFile::load(&path).is_err(), "{text}");
+        }
+        assert!(
+            ConfigFile::load(&dir.path().join("none.toml"))
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -1,7 +1,36 @@
 use std::time::Duration;
 
 // Hugging Face repo (Whisper models for whisper.cpp)
-pub const HF_REPO_API: &str =
-    "https://huggingface.co/api/models/ggerganov/whisper.cpp?expand=siblings";
-pub const HF_RESOLVE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/"; // + rfilename
+pub const HF_DEFAULT_ENDPOINT: &str = "https://huggingface.co";
+pub const HF_DEFAULT_REPO: &str = "ggerganov/whisper.cpp";
 pub const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60); // 24h
+
+// `--output-template` default: the input's file name, or the downloaded title
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "{stem}";
+
+// Interactive picker switches to type-to-filter above this many entries
+pub const FUZZY_PICKER_THRESHOLD: usize = 12;
+
+// stderr lines of a failed tool kept in the error message
+pub const STDERR_TAIL_LINES: usize = 20;
+
+// whisper keeps at most half of its 448-token text context for the initial prompt
+pub const PROMPT_TOKEN_BUDGET: usize = 224;
+
+// Whole segments whisper tends to invent over silence or music (matched ignoring
+// case and punctuation); `--hallucination-phrases FILE` replaces this list
+pub const HALLUCINATION_PHRASES: &[&str] = &[
+    "[BLANK_AUDIO]",
+    "Thanks for watching!",
+    "Thank you for watching.",
+    "Thank you so much for watching.",
+    "Please subscribe to my channel.",
+    "Don't forget to like and subscribe.",
+    "See you in the next video.",
+    "Subtitles by the Amara.org community",
+    "Transcription by CastingWords",
+    "Untertitel der Amara.org-Community",
+    "Sous-titres réalisés par la communauté d'Amara.org",
+    "Продолжение следует...",
+    "ご視聴ありがとうございました",
+];
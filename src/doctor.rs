@@ -0,0 +1,540 @@
+//! `vid2txt doctor`: checks the external tools, directories and resources vid2txt
+//! relies on, so a broken setup is found before a long run rather than during it.
+
+use crate::backend::WHISPER_CLI_FLAGS;
+use crate::clock;
+use crate::config::Resolved;
+use crate::fs_utils::whisper_models_dir;
+use crate::hf::{cache_dir_path, catalog_cache_info};
+use crate::models::format_size;
+use crate::recommend::Hardware;
+use crate::tools::{Tool, Tools};
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as PCommand;
+use sysinfo::Disks;
+
+/// yt-dlp releases older than this often fail on sites that changed since.
+const YTDLP_MAX_AGE_DAYS: i64 = 180;
+/// Room for a large model download plus its temp file.
+const MIN_FREE_MODELS_DIR: u64 = 4 * 1024 * 1024 * 1024;
+/// Room for a few hours of 16 kHz mono WAV.
+const MIN_FREE_OUT_DIR: u64 = 1024 * 1024 * 1024;
+/// Below this even the small models won't load comfortably.
+const MIN_FREE_RAM: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    checks: Vec<Check>,
+    passed: usize,
+    warnings: usize,
+    failed: usize,
+}
+
+pub fn run(json: bool, tools: &Tools, config: &Resolved) -> Result<()> {
+    let mut checks = vec![config_check(config)];
+    checks.push(tool_check(
+        tools,
+        Tool::Ffmpeg,
+        Some("-version"),
+        Status::Fail,
+        "audio extraction",
+    ));
+    checks.push(tool_check(
+        tools,
+        Tool::Ffprobe,
+        Some("-version"),
+        Status::Warn,
+        "media durations (progress bars, model recommendation)",
+    ));
+    checks.push(ytdlp_check(tools));
+    let whisper = tool_check(
+        tools,
+        Tool::WhisperCli,
+        None,
+        Status::Warn,
+        "the default whisper-cli backend",
+    );
+    checks.push(whisper);
+    if let Some(path) = tools.get(Tool::WhisperCli) {
+        checks.extend(whisper_flag_checks(path));
+    }
+    checks.push(tool_check(
+        tools,
+        Tool::WhisperServer,
+        None,
+        Status::Warn,
+        "--backend whisper-server",
+    ));
+
+    let models_dir = whisper_models_dir(tools);
+    checks.push(match &models_dir {
+        Ok(dir) => writable_check("models dir", dir),
+        Err(e) => Check::new("models dir", Status::Fail, format!("{e:#}")),
+    });
+    checks.push(cache_check());
+    if let Ok(dir) = &models_dir {
+        checks.push(disk_check("disk (models)", dir, MIN_FREE_MODELS_DIR));
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        checks.push(disk_check("disk (output)", &cwd, MIN_FREE_OUT_DIR));
+    }
+    checks.push(memory_check());
+
+    let count = |s: Status| checks.iter().filter(|c| c.status == s).count();
+    let report = Report {
+        passed: count(Status::Pass),
+        warnings: count(Status::Warn),
+        failed: count(Status::Fail),
+        checks,
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    if report.failed > 0 {
+        return Err(anyhow!("{} check(s) failed", report.failed));
+    }
+    Ok(())
+}
+
+/// Config files that failed to parse; they are ignored for this run.
+fn config_check(config: &Resolved) -> Check {
+    if !config.errors.is_empty() {
+        return Check::new("config", Status::Fail, config.errors.join("; "))
+            .hint("fix the file; `vid2txt config show` lists the config files");
+    }
+    let detail = if config.files.is_empty() {
+        "no config files".to_string()
+    } else {
+        config
+            .files
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    Check::new("config", Status::Pass, detail)
+}
+
+fn print_report(report: &Report) {
+    let width = report
+        .checks
+        .iter()
+        .map(|c| c.name.len())
+        .max()
+        .unwrap_or(0);
+    for c in &report.checks {
+        let icon = match c.status {
+            Status::Pass => "✅",
+            Status::Warn => "⚠️ ",
+            Status::Fail => "❌",
+        };
+        println!("{icon} {:width$}  {}", c.name, c.detail);
+        if let Some(h) = &c.hint {
+            println!("   {:width$}  → {h}", "");
+        }
+    }
+    println!();
+    println!(
+        "{} passed, {} warning(s), {} failed",
+        report.passed, report.warnings, report.failed
+    );
+}
+
+/// Locate `tool` and report the version its `version_flag` prints (whisper.cpp's
+/// tools have none). `missing` is the status when it isn't installed.
+fn tool_check(
+    tools: &Tools,
+    tool: Tool,
+    version_flag: Option<&str>,
+    missing: Status,
+    needed_for: &str,
+) -> Check {
+    let name = tool.name();
+    let Some(path) = tools.get(tool) else {
+        return Check::new(
+            name,
+            missing,
+            format!("not found in PATH (looked for {})", tool.names().join(", ")),
+        )
+        .hint(format!("needed for {needed_for}"));
+    };
+    let Some(flag) = version_flag else {
+        return Check::new(name, Status::Pass, path.display().to_string());
+    };
+    match tool_output(path, flag) {
+        Some(out) => {
+            let version = version_line(&out);
+            Check::new(
+                name,
+                Status::Pass,
+                format!("{version} ({})", path.display()),
+            )
+        }
+        None => Check::new(
+            name,
+            Status::Fail,
+            format!("{} does not run", path.display()),
+        )
+        .hint(format!("needed for {needed_for}")),
+    }
+}
+
+/// stdout and stderr of `bin arg`, or `None` if it can't be started.
+fn tool_output(bin: &Path, arg: &str) -> Option<String> {
+    let out = PCommand::new(bin).arg(arg).output().ok()?;
+    Some(format!(
+        "{}{}",
+        String::from_utf8_lossy(&out.stdout),
+        String::from_utf8_lossy(&out.stderr)
+    ))
+}
+
+/// "ffmpeg version 6.1.1-3ubuntu5 Copyright…" → "6.1.1-3ubuntu5"; "2024.08.06" as is.
+fn version_line(output: &str) -> String {
+    match output.lines().map(str::trim).find(|l| !l.is_empty()) {
+        None => "version unknown".to_string(),
+        Some(line) => match line.split_once(" version ") {
+            Some((_, rest)) => rest.split_whitespace().next().unwrap_or(rest).to_string(),
+            None => line.to_string(),
+        },
+    }
+}
+
+fn ytdlp_check(tools: &Tools) -> Check {
+    let check = tool_check(
+        tools,
+        Tool::YtDlp,
+        Some("--version"),
+        Status::Warn,
+        "URL inputs",
+    );
+    if check.status != Status::Pass {
+        return check;
+    }
+    let Some(age) = check
+        .detail
+        .split_whitespace()
+        .next()
+        .and_then(release_age_days)
+    else {
+        return check;
+    };
+    if age > YTDLP_MAX_AGE_DAYS {
+        Check {
+            status: Status::Warn,
+            ..check
+        }
+        .hint(format!(
+            "released {age} days ago; sites change often, update with `yt-dlp -U` or your package manager"
+        ))
+    } else {
+        check
+    }
+}
+
+/// Age of a `YYYY.MM.DD[.N]` release in days.
+fn release_age_days(version: &str) -> Option<i64> {
+    let mut parts = version.split('.').map(|p| p.parse::<i64>().ok());
+    let (y, m, d) = (parts.next()??, parts.next()??, parts.next()??);
+    Some(clock::today_days() - clock::days_from_civil(y, m, d))
+}
+
+/// Does this whisper-cli understand the flags vid2txt passes?
+fn whisper_flag_checks(whisper_cli: &Path) -> Vec<Check> {
+    tool_output(whisper_cli, "--help")
+        .map(|help| help_flag_checks(&help))
+        .unwrap_or_default()
+}
+
+/// [`whisper_flag_checks`] for the `--help` text of whisper-cli.
+fn help_flag_checks(help: &str) -> Vec<Check> {
+    let known: Vec<&str> = help
+        .split_whitespace()
+        .map(|w| w.trim_end_matches(','))
+        .filter(|w| w.starts_with('-'))
+        .collect();
+    let missing: Vec<_> = WHISPER_CLI_FLAGS
+        .iter()
+        .filter(|(flag, ..)| !known.contains(flag))
+        .collect();
+    if missing.is_empty() {
+        return vec![Check::new(
+            "whisper-cli flags",
+            Status::Pass,
+            format!(
+                "all {} flags vid2txt uses are supported",
+                WHISPER_CLI_FLAGS.len()
+            ),
+        )];
+    }
+    let mut checks: Vec<Check> = missing
+        .into_iter()
+        .map(|(flag, used_for, always)| {
+            let status = if *always { Status::Fail } else { Status::Warn };
+            Check::new(
+                format!("whisper-cli {flag}"),
+                status,
+                format!("not supported; needed for {used_for}"),
+            )
+        })
+        .collect();
+    if let Some(last) = checks.pop() {
+        checks.push(
+            last.hint(
+                "this whisper.cpp build is too old; rebuild whisper-cli from a recent release",
+            ),
+        );
+    }
+    checks
+}
+
+/// A directory is usable if we can create a file in it, or in the nearest existing
+/// parent when it doesn't exist yet.
+fn writable_check(name: &str, dir: &Path) -> Check {
+    let existing = dir.ancestors().find(|p| p.is_dir());
+    let Some(existing) = existing else {
+        return Check::new(
+            name,
+            Status::Fail,
+            format!("{} has no existing parent", dir.display()),
+        );
+    };
+    match tempfile::tempfile_in(existing) {
+        Ok(_) if existing == dir => {
+            Check::new(name, Status::Pass, format!("{} is writable", dir.display()))
+        }
+        Ok(_) => Check::new(
+            name,
+            Status::Pass,
+            format!("{} will be created on first download", dir.display()),
+        ),
+        Err(e) => Check::new(
+            name,
+            Status::Fail,
+            format!("{} is not writable: {e}", existing.display()),
+        )
+        .hint("models can't be downloaded; fix the permissions or place models there by hand"),
+    }
+}
+
+/// Every cached model list must parse; a broken one would be refetched on each run.
+fn cache_check() -> Check {
+    let dir = match cache_dir_path() {
+        Ok(d) => d,
+        Err(e) => return Check::new("cache", Status::Warn, format!("{e:#}")),
+    };
+    if !dir.exists() {
+        return Check::new("cache", Status::Pass, format!("{} (empty)", dir.display()));
+    }
+    let entries: Vec<PathBuf> = match fs::read_dir(&dir) {
+        Ok(rd) => rd.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(e) => {
+            return Check::new(
+                "cache",
+                Status::Fail,
+                format!("{} is not readable: {e}", dir.display()),
+            );
+        }
+    };
+    let lists: Vec<&PathBuf> = entries
+        .iter()
+        .filter(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy())
+                .is_some_and(|n| n.starts_with("models-") && n.ends_with(".json"))
+        })
+        .collect();
+    let broken: Vec<String> = lists
+        .iter()
+        .filter(|p| catalog_cache_info(p).is_none())
+        .map(|p| {
+            p.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    if broken.is_empty() {
+        Check::new(
+            "cache",
+            Status::Pass,
+            format!("{} ({} model list(s))", dir.display(), lists.len()),
+        )
+    } else {
+        Check::new(
+            "cache",
+            Status::Warn,
+            format!("unreadable model list(s): {}", broken.join(", ")),
+        )
+        .hint("run `vid2txt cache clear`")
+    }
+}
+
+/// Free space on the disk holding `path`.
+fn disk_check(name: &str, path: &Path, min_free: u64) -> Check {
+    let path = path
+        .ancestors()
+        .find(|p| p.exists())
+        .and_then(|p| p.canonicalize().ok())
+        .unwrap_or_else(|| path.to_path_buf());
+    let disks = Disks::new_with_refreshed_list();
+    let Some(disk) = disks
+        .list()
+        .iter()
+        .filter(|d| path.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+    else {
+        return Check::new(
+            name,
+            Status::Warn,
+            format!("no disk found for {}", path.display()),
+        );
+    };
+    let free = disk.available_space();
+    let detail = format!(
+        "{} free of {} at {}",
+        format_size(free),
+        format_size(disk.total_space()),
+        disk.mount_point().display()
+    );
+    if free < min_free {
+        Check::new(name, Status::Warn, detail)
+            .hint(format!("less than {} free", format_size(min_free)))
+    } else {
+        Check::new(name, Status::Pass, detail)
+    }
+}
+
+fn memory_check() -> Check {
+    let hw = Hardware::detect();
+    let detail = hw.summary();
+    if hw.available_memory > 0 && hw.available_memory < MIN_FREE_RAM {
+        Check::new("memory", Status::Warn, detail)
+            .hint("only the tiny/base models (or quantized ones) are likely to fit")
+    } else {
+        Check::new("memory", Status::Pass, detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The options part of a recent `whisper-cli --help`.
+    const HELP: &str = "
+usage: whisper-cli [options] file0 file1 ...
+supported audio formats: flac, mp3, ogg, wav
+
+options:
+  -h,        --help              [default] show this help message and exit
+  -t N,      --threads N         [4      ] number of threads to use during computation
+  -p N,      --processors N      [1      ] number of processors to use during computation
+  -bo N,     --best-of N         [5      ] number of best candidates to keep
+  -bs N,     --beam-size N       [5      ] beam size for beam search
+  -tpi N,    --temperature-inc N [0.20   ] The increment of temperature, between 0 and 1
+  -nth N,    --no-speech-thold N [0.60   ] no speech threshold
+  -sow,      --split-on-word     [false  ] split on word rather than on token
+  -di,       --diarize           [false  ] stereo audio diarization
+  -otxt,     --output-txt        [false  ] output result in a text file
+  -ojf,      --output-json-full  [false  ] include more information in the JSON file
+  -of FNAME, --output-file FNAME [       ] output file path (without file extension)
+  -pp,       --print-progress    [false  ] print progress
+  -dl,       --detect-language   [false  ] exit after automatically detecting language
+             --prompt PROMPT     [       ] initial prompt (max n_text_ctx/2 tokens)
+  -m FNAME,  --model FNAME       [models/ggml-base.en.bin] model path
+";
+
+    #[test]
+    fn reads_version_lines() {
+        for (output, version) in [
+            (
+                "ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023 the FFmpeg developers\nbuilt with gcc 13",
+                "6.1.1-3ubuntu5",
+            ),
+            (
+                "ffmpeg version n7.0.2 Copyright (c) 2000-2024 the FFmpeg developers",
+                "n7.0.2",
+            ),
+            ("2024.08.06\n", "2024.08.06"),
+            ("\n  2025.01.15.232435\n", "2025.01.15.232435"),
+            ("", "version unknown"),
+        ] {
+            assert_eq!(version_line(output), version, "{output}");
+        }
+    }
+
+    #[test]
+    fn ages_yt_dlp_releases() {
+        let (y, m, d) = clock::civil_from_days(clock::today_days() - 200);
+        let old = format!("{y}.{m:02}.{d:02}");
+        assert_eq!(release_age_days(&old), Some(200));
+        assert_eq!(release_age_days(&format!("{old}.232435")), Some(200));
+        let (y, m, d) = clock::civil_from_days(clock::today_days());
+        assert_eq!(release_age_days(&format!("{y}.{m:02}.{d:02}")), Some(0));
+        for version in ["stable", "1.2", "2024.x.06", ""] {
+            assert_eq!(release_age_days(version), None, "{version}");
+        }
+    }
+
+    #[test]
+    fn recent_whisper_cli_has_every_flag() {
+        let checks = help_flag_checks(HELP);
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].status, Status::Pass);
+    }
+
+    #[test]
+    fn reports_flags_an_old_build_lacks() {
+        let old: String = HELP
+            .lines()
+            .filter(|l| !l.contains("-dl,") && !l.contains("-ojf,"))
+            .map(|l| format!("{l}\n"))
+            .collect();
+        let checks = help_flag_checks(&old);
+        let names: Vec<_> = checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["whisper-cli -ojf", "whisper-cli -dl"]);
+        // every run needs the JSON output; detection is optional
+        assert_eq!(checks[0].status, Status::Fail);
+        assert_eq!(checks[1].status, Status::Warn);
+        assert!(checks[1].detail.contains("--language auto"));
+        assert!(checks[1].hint.as_deref().unwrap().contains("too old"));
+    }
+}
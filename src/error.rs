@@ -0,0 +1,208 @@
+//! Failure categories, so scripts can tell a failed download from a missing model
+//! by the exit code instead of parsing messages.
+
+use crate::backend::OutOfMemory;
+use std::fmt;
+
+/// A classified failure. Anything else (I/O errors, bugs) exits with code 1.
+#[derive(Debug)]
+pub enum Vid2txtError {
+    /// An input or option vid2txt can't work with (exit code 2, like usage errors)
+    InvalidInput(String),
+    /// A required external tool isn't installed or its configured path is wrong (3)
+    MissingTool(String),
+    /// No usable model: nothing matches or the catalog is empty (4)
+    ModelResolution(String),
+    /// A network request failed, or `--offline` forbids it (5)
+    Network(String),
+    /// yt-dlp couldn't fetch the audio (6)
+    Download {
+        message: String,
+        stderr: Vec<String>,
+    },
+    /// ffmpeg couldn't extract or convert the audio (7)
+    Extraction {
+        message: String,
+        stderr: Vec<String>,
+    },
+    /// The backend failed to transcribe (8)
+    Transcription {
+        message: String,
+        stderr: Vec<String>,
+    },
+    /// The user aborted (130, like a shell's SIGINT status)
+    Cancelled,
+}
+
+impl Vid2txtError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Vid2txtError::InvalidInput(_) => 2,
+            Vid2txtError::MissingTool(_) => 3,
+            Vid2txtError::ModelResolution(_) => 4,
+            Vid2txtError::Network(_) => 5,
+            Vid2txtError::Download { .. } => 6,
+            Vid2txtError::Extraction { .. } => 7,
+            Vid2txtError::Transcription { .. } => 8,
+            Vid2txtError::Cancelled => 130,
+        }
+    }
+
+    /// Stable name for `--json` error reports.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Vid2txtError::InvalidInput(_) => "invalid_input",
+            Vid2txtError::MissingTool(_) => "missing_tool",
+            Vid2txtError::ModelResolution(_) => "model_resolution",
+            Vid2txtError::Network(_) => "network",
+            Vid2txtError::Download { .. } => "download",
+            Vid2txtError::Extraction { .. } => "extraction",
+            Vid2txtError::Transcription { .. } => "transcription",
+            Vid2txtError::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for Vid2txtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Vid2txtError::InvalidInput(m)
+            | Vid2txtError::MissingTool(m)
+            | Vid2txtError::ModelResolution(m)
+            | Vid2txtError::Network(m) => f.write_str(m),
+            Vid2txtError::Download { message, stderr }
+            | Vid2txtError::Extraction { message, stderr }
+            | Vid2txtError::Transcription { message, stderr } => {
+                f.write_str(message)?;
+                if !stderr.is_empty() {
+                    f.write_str("; last output:")?;
+                    for line in stderr {
+                        write!(f, "\n    {line}")?;
+                    }
+                }
+                Ok(())
+            }
+            Vid2txtError::Cancelled => f.write_str("Cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Vid2txtError {}
+
+/// `(kind, exit code)` of any error: the first [`Vid2txtError`] or [`OutOfMemory`]
+/// found in its chain, else `("other", 1)`.
+pub fn classify(e: &anyhow::Error) -> (&'static str, u8) {
+    if let Some(err) = e.downcast_ref::<Vid2txtError>() {
+        return (err.kind(), err.exit_code());
+    }
+    if e.downcast_ref::<OutOfMemory>().is_some() {
+        return ("out_of_memory", 8);
+    }
+    for cause in e.chain() {
+        if let Some(err) = cause.downcast_ref::<Vid2txtError>() {
+            return (err.kind(), err.exit_code());
+        }
+        if cause.downcast_ref::<OutOfMemory>().is_some() {
+            return ("out_of_memory", 8);
+        }
+    }
+    ("other", 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::request_error;
+    use anyhow::Context;
+
+    fn failed(message: &str) -> (String, Vec<String>) {
+        (message.to_string(), vec!["last line".to_string()])
+    }
+
+    #[test]
+    fn every_kind_has_its_exit_code() {
+        let (message, stderr) = failed("failed");
+        for (err, kind, code) in [
+            (Vid2txtError::InvalidInput("x".into()), "invalid_input", 2),
+            (Vid2txtError::MissingTool("x".into()), "missing_tool", 3),
+            (
+                Vid2txtError::ModelResolution("x".into()),
+                "model_resolution",
+                4,
+            ),
+            (Vid2txtError::Network("x".into()), "network", 5),
+            (
+                Vid2txtError::Download {
+                    message: message.clone(),
+                    stderr: stderr.clone(),
+                },
+                "download",
+                6,
+            ),
+            (
+                Vid2txtError::Extraction {
+                    message: message.clone(),
+                    stderr: stderr.clone(),
+                },
+                "extraction",
+                7,
+            ),
+            (
+                Vid2txtError::Transcription { message, stderr },
+                "transcription",
+                8,
+            ),
+            (Vid2txtError::Cancelled, "cancelled", 130),
+        ] {
+            assert_eq!(classify(&err.into()), (kind, code));
+        }
+        let oom = OutOfMemory {
+            backend: "whisper-cli",
+        };
+        assert_eq!(classify(&oom.into()), ("out_of_memory", 8));
+        assert_eq!(classify(&anyhow::anyhow!("boom")), ("other", 1));
+    }
+
+    #[test]
+    fn classifies_through_context() {
+        let err = Err::<(), _>(OutOfMemory {
+            backend: "in-process",
+        })
+        .context("Transcription of talk.mp4 failed")
+        .unwrap_err();
+        assert_eq!(classify(&err), ("out_of_memory", 8));
+
+        let err = Err::<(), _>(Vid2txtError::Cancelled)
+            .context("downloading")
+            .context("input 2")
+            .unwrap_err();
+        assert_eq!(classify(&err), ("cancelled", 130));
+
+        // the outermost vid2txt error wins
+        let err = anyhow::Error::from(Vid2txtError::Network("offline".into()))
+            .context(Vid2txtError::ModelResolution("no model".into()));
+        assert_eq!(classify(&err), ("model_resolution", 4));
+    }
+
+    #[test]
+    fn classifies_request_errors() {
+        // nothing listens on port 1
+        let e = reqwest::blocking::get("http://127.0.0.1:1/").unwrap_err();
+        let err = request_error(e, "Server unreachable".into()).context("input 1");
+        assert_eq!(classify(&err), ("network", 5));
+
+        let e = reqwest::blocking::get("not a url").unwrap_err();
+        let err = request_error(e, "Bad request".into());
+        assert_eq!(classify(&err), ("transcription", 8));
+    }
+
+    #[test]
+    fn shows_the_tool_output() {
+        let (message, stderr) = failed("ffmpeg failed");
+        let err = Vid2txtError::Extraction { message, stderr };
+        assert_eq!(
+            err.to_string(),
+            "ffmpeg failed; last output:\n    last line"
+        );
+    }
+}
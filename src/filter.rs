@@ -0,0 +1,376 @@
+//! Post-processing that drops what whisper tends to invent: repetition loops, stock
+//! outro phrases over silence/music, and segments it flags as probably not speech.
+
+use crate::constants::HALLUCINATION_PHRASES;
+use crate::transcript::{Segment, Transcript};
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Longest phrase (in words) considered when looking for loops.
+const MAX_NGRAM: usize = 8;
+
+/// Repeats inside a sentence make a loop only once they span this many words:
+/// "no, no, no" and "ha ha ha ha" are speech, a word said ten times in a row is not.
+/// Whole sentences and segments loop at `max_repeats`.
+const MIN_LOOP_WORDS: usize = 10;
+
+/// Back-to-back copies of an `n`-word phrase inside a sentence that make a loop.
+fn loop_repeats(n: usize, max_repeats: usize) -> usize {
+    max_repeats.max(MIN_LOOP_WORDS.div_ceil(n.max(1)))
+}
+
+fn ends_sentence(token: &str) -> bool {
+    token.ends_with(['.', '!', '?', '…'])
+}
+
+#[derive(Debug, Clone)]
+pub struct FilterOptions {
+    /// A sentence or segment repeated back to back this many times is a loop
+    pub max_repeats: usize,
+    /// Drop segments whose no-speech probability is at least this
+    pub no_speech_threshold: f32,
+    /// Normalized (see [`normalize`]) phrases removed when they make up a whole segment
+    phrases: Vec<String>,
+}
+
+impl FilterOptions {
+    /// `phrases_file` (one phrase per line) replaces the built-in phrase list.
+    pub fn new(
+        max_repeats: usize,
+        no_speech_threshold: f32,
+        phrases_file: Option<&Path>,
+    ) -> Result<Self> {
+        let phrases = match phrases_file {
+            Some(path) => fs::read_to_string(path)
+                .with_context(|| {
+                    format!("Failed to read hallucination phrases {}", path.display())
+                })?
+                .lines()
+                .map(normalize)
+                .filter(|p| !p.is_empty())
+                .collect(),
+            None => HALLUCINATION_PHRASES.iter().map(|p| normalize(p)).collect(),
+        };
+        Ok(Self {
+            max_repeats,
+            no_speech_threshold,
+            phrases,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Reason {
+    Repetition,
+    KnownPhrase,
+    NoSpeech,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Reason::Repetition => "repetition",
+            Reason::KnownPhrase => "known hallucination",
+            Reason::NoSpeech => "no speech",
+        })
+    }
+}
+
+/// A span taken out of the transcript.
+#[derive(Debug, Clone)]
+pub struct Removal {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub reason: Reason,
+}
+
+/// Clean `transcript` in place and return what was removed, in time order.
+pub fn clean(transcript: &mut Transcript, opts: &FilterOptions) -> Vec<Removal> {
+    let mut removed = Vec::new();
+    let mut kept: Vec<Segment> = Vec::with_capacity(transcript.segments.len());
+
+    for seg in std::mem::take(&mut transcript.segments) {
+        let norm = normalize(&seg.text);
+        let reason = if seg
+            .no_speech_prob
+            .is_some_and(|p| p >= opts.no_speech_threshold)
+        {
+            Some(Reason::NoSpeech)
+        } else if !norm.is_empty() && opts.phrases.contains(&norm) {
+            Some(Reason::KnownPhrase)
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => removed.push(removal(&seg, reason)),
+            None => kept.push(seg),
+        }
+    }
+
+    // Loops across segments: keep the first of a run of identical segments
+    let mut deduped: Vec<Segment> = Vec::with_capacity(kept.len());
+    let mut i = 0;
+    while i < kept.len() {
+        let norm = normalize(&kept[i].text);
+        let run = kept[i..]
+            .iter()
+            .take_while(|s| !norm.is_empty() && normalize(&s.text) == norm)
+            .count()
+            .max(1);
+        let is_loop = run >= opts.max_repeats;
+        if is_loop {
+            for dup in &kept[i + 1..i + run] {
+                removed.push(removal(dup, Reason::Repetition));
+            }
+        }
+        let keep = if is_loop { 1 } else { run };
+        deduped.extend(kept[i..i + keep].iter().cloned());
+        i += run;
+    }
+
+    // Loops inside a segment: collapse "a b a b a b a b a b" to "a b"
+    for seg in &mut deduped {
+        if let Some(text) = collapse_repeats(seg, opts.max_repeats) {
+            removed.push(Removal {
+                start: seg.start,
+                end: seg.end,
+                text,
+                reason: Reason::Repetition,
+            });
+        }
+    }
+
+    transcript.segments = deduped;
+    removed.sort_by(|a, b| a.start.total_cmp(&b.start));
+    removed
+}
+
+fn removal(seg: &Segment, reason: Reason) -> Removal {
+    Removal {
+        start: seg.start,
+        end: seg.end,
+        text: seg.text.clone(),
+        reason,
+    }
+}
+
+/// Remove back-to-back repeats of any 1..=[`MAX_NGRAM`]-word phrase, keeping one copy:
+/// whole sentences repeated `max_repeats` times, other phrases repeated
+/// [`loop_repeats`] times. Returns the removed text, if any.
+fn collapse_repeats(seg: &mut Segment, max_repeats: usize) -> Option<String> {
+    let tokens: Vec<&str> = seg.text.split_whitespace().collect();
+    let norm: Vec<String> = tokens.iter().map(|t| normalize(t)).collect();
+    let mut keep = vec![true; tokens.len()];
+    // the segment's end closes a sentence too
+    let closes_sentence = |j: usize| j + 1 == tokens.len() || ends_sentence(tokens[j]);
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let mut step = 1;
+        for n in 1..=MAX_NGRAM {
+            if i + 2 * n > tokens.len() {
+                break;
+            }
+            if norm[i..i + n].iter().all(String::is_empty) {
+                break;
+            }
+            let reps = (1..)
+                .take_while(|r| {
+                    let at = i + r * n;
+                    at + n <= tokens.len() && norm[at..at + n] == norm[i..i + n]
+                })
+                .count()
+                + 1;
+            let sentences = if i == 0 || ends_sentence(tokens[i - 1]) {
+                (1..=reps)
+                    .take_while(|r| closes_sentence(i + r * n - 1))
+                    .count()
+            } else {
+                0
+            };
+            let reps = if sentences >= max_repeats {
+                sentences
+            } else if reps >= loop_repeats(n, max_repeats) {
+                reps
+            } else {
+                continue;
+            };
+            keep[i + n..i + reps * n].fill(false);
+            step = reps * n;
+            break;
+        }
+        i += step;
+    }
+
+    if keep.iter().all(|k| *k) {
+        return None;
+    }
+    let pick = |want: bool| {
+        tokens
+            .iter()
+            .zip(&keep)
+            .filter(|(_, k)| **k == want)
+            .map(|(t, _)| *t)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let (text, removed) = (pick(true), pick(false));
+    // word timings can only follow when they line up with the text's words
+    if seg.words.len() == tokens.len() {
+        let mut k = keep.iter();
+        seg.words.retain(|_| *k.next().unwrap_or(&true));
+    }
+    seg.text = text;
+    Some(removed)
+}
+
+/// Lowercase, letters and digits only, single spaces.
+fn normalize(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `[mm:ss.s–mm:ss.s]`
+pub fn format_span(r: &Removal) -> String {
+    let ts = |t: f64| format!("{:02}:{:04.1}", (t / 60.0) as u64, t % 60.0);
+    format!("[{}–{}]", ts(r.start), ts(r.end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Word;
+
+    fn seg(start: f64, text: &str) -> Segment {
+        Segment {
+            start,
+            end: start + 1.0,
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    fn clean_texts(texts: &[&str]) -> (Vec<String>, Vec<Removal>) {
+        let mut t = Transcript {
+            segments: texts
+                .iter()
+                .enumerate()
+                .map(|(i, s)| seg(i as f64, s))
+                .collect(),
+            ..Default::default()
+        };
+        let removed = clean(&mut t, &FilterOptions::new(3, 0.6, None).unwrap());
+        (t.segments.into_iter().map(|s| s.text).collect(), removed)
+    }
+
+    #[test]
+    fn keeps_legitimate_repetition() {
+        for text in [
+            "No, no, no, that's wrong.",
+            "It was very very very good.",
+            "ha ha ha ha",
+            "we will, we will, we will rock you",
+        ] {
+            let (texts, removed) = clean_texts(&[text]);
+            assert_eq!(texts, [text]);
+            assert!(removed.is_empty(), "{text}");
+        }
+    }
+
+    #[test]
+    fn collapses_word_loop() {
+        let text = format!("Okay {}", ["thank you"; 6].join(" "));
+        let (texts, removed) = clean_texts(&[&text]);
+        assert_eq!(texts, ["Okay thank you"]);
+        assert_eq!(removed.len(), 1);
+        assert!(matches!(removed[0].reason, Reason::Repetition));
+    }
+
+    #[test]
+    fn collapses_single_word_loop() {
+        let text = ["la"; 12].join(" ");
+        let (texts, _) = clean_texts(&[&text]);
+        assert_eq!(texts, ["la"]);
+    }
+
+    #[test]
+    fn collapses_repeated_sentences() {
+        let (texts, removed) = clean_texts(&["Thank you. Thank you. Thank you."]);
+        assert_eq!(texts, ["Thank you."]);
+        assert_eq!(removed[0].text, "Thank you. Thank you.");
+        let (texts, _) = clean_texts(&["Bye! Thank you. Thank you. Thank you"]);
+        assert_eq!(texts, ["Bye! Thank you."]);
+        // below --max-repeats
+        let (texts, removed) = clean_texts(&["Thank you. Thank you."]);
+        assert_eq!(texts, ["Thank you. Thank you."]);
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn max_repeats_is_the_segment_threshold() {
+        let (texts, removed) = clean_texts(&["Yes.", "Yes.", "Maybe.", "Yes."]);
+        assert_eq!(texts.len(), 4);
+        assert!(removed.is_empty());
+        let (texts, removed) = clean_texts(&["Thank you.", "Thank you.", "Thank you."]);
+        assert_eq!(texts, ["Thank you."]);
+        assert_eq!(removed.len(), 2);
+    }
+
+    #[test]
+    fn dedupes_repeated_long_segments() {
+        let line = "and then we went back to the house";
+        let (texts, removed) = clean_texts(&[line, line, line, "the end"]);
+        assert_eq!(texts, [line, "the end"]);
+        assert_eq!(removed.len(), 2);
+    }
+
+    #[test]
+    fn drops_known_phrases_and_no_speech() {
+        let mut t = Transcript {
+            segments: vec![
+                seg(0.0, "Hello there."),
+                seg(1.0, "Thanks for watching!"),
+                Segment {
+                    no_speech_prob: Some(0.9),
+                    ..seg(2.0, "mm")
+                },
+            ],
+            ..Default::default()
+        };
+        let removed = clean(&mut t, &FilterOptions::new(3, 0.6, None).unwrap());
+        assert_eq!(t.segments.len(), 1);
+        assert!(matches!(removed[0].reason, Reason::KnownPhrase));
+        assert!(matches!(removed[1].reason, Reason::NoSpeech));
+    }
+
+    #[test]
+    fn word_timings_follow_collapsed_text() {
+        let tokens = ["la"; 12];
+        let mut s = seg(0.0, &tokens.join(" "));
+        s.words = tokens
+            .iter()
+            .enumerate()
+            .map(|(i, w)| Word {
+                start: i as f64,
+                end: i as f64 + 0.5,
+                text: w.to_string(),
+                probability: None,
+            })
+            .collect();
+        assert!(collapse_repeats(&mut s, 3).is_some());
+        assert_eq!(s.words.len(), 1);
+    }
+}
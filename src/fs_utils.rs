@@ -1,3 +1,4 @@
+use crate::tools::{Tool, Tools};
 use anyhow::{Context, Result, anyhow};
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -7,20 +8,31 @@ pub fn find_first_with_ext(dir: &Path, ext: &str) -> Result<Option<PathBuf>> {
     let ext_lc = ext.to_ascii_lowercase();
     for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
         let p = entry.path();
-        if p.is_file() {
-            if let Some(e) = p.extension().and_then(|s| s.to_str()) {
-                if e.eq_ignore_ascii_case(&ext_lc) {
-                    return Ok(Some(p.to_path_buf()));
-                }
-            }
+        if p.is_file()
+            && let Some(e) = p.extension().and_then(|s| s.to_str())
+            && e.eq_ignore_ascii_case(&ext_lc)
+        {
+            return Ok(Some(p.to_path_buf()));
         }
     }
     Ok(None)
 }
 
-pub fn whisper_models_dir() -> Result<PathBuf> {
-    let cli = which::which("whisper-cli").context("Cannot locate whisper-cli in PATH")?;
-    let parent = cli
+pub fn whisper_models_dir(tools: &Tools) -> Result<PathBuf> {
+    // whisper-server ships next to whisper-cli; either locates the models dir
+    let bin = match tools
+        .get(Tool::WhisperCli)
+        .or_else(|| tools.get(Tool::WhisperServer))
+    {
+        Some(bin) => bin,
+        // No whisper.cpp install (e.g. only the in-process build is deployed)
+        None => {
+            return dirs::data_dir()
+                .map(|d| d.join("vid2txt").join("models"))
+                .context("Cannot locate whisper-cli (or whisper-server) in PATH");
+        }
+    };
+    let parent = bin
         .parent()
         .ok_or_else(|| anyhow!("Unexpected whisper-cli path"))?;
     Ok(parent.join("models"))
@@ -29,3 +41,35 @@ pub fn whisper_models_dir() -> Result<PathBuf> {
 pub fn create_dir_all(p: &Path) -> Result<()> {
     fs::create_dir_all(p).with_context(|| format!("Failed to create dir: {}", p.display()))
 }
+
+/// Create the directory `p` will live in (templates may name subdirectories).
+pub fn create_parent_dir(p: &Path) -> Result<()> {
+    match p.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => create_dir_all(dir),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `a` and `b` name the same file; `b` need not exist yet.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    let canonical = |p: &Path| {
+        p.canonicalize().ok().or_else(|| {
+            let dir = p.parent().filter(|d| !d.as_os_str().is_empty());
+            Some(
+                dir.unwrap_or(Path::new("."))
+                    .canonicalize()
+                    .ok()?
+                    .join(p.file_name()?),
+            )
+        })
+    };
+    matches!((canonical(a), canonical(b)), (Some(a), Some(b)) if a == b)
+}
+
+/// Move a file, falling back to copy + delete across filesystems.
+pub fn move_file(from: &Path, to: &Path) -> Result<()> {
+    create_parent_dir(to)?;
+    fs::rename(from, to)
+        .or_else(|_| fs::copy(from, to).and_then(|_| fs::remove_file(from)))
+        .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))
+}
@@ -0,0 +1,256 @@
+use crate::constants::PROMPT_TOKEN_BUDGET;
+use crate::transcript::Transcript;
+use anyhow::{Context, Result, anyhow};
+use regex::{Regex, RegexBuilder};
+use std::fs;
+use std::path::Path;
+
+/// Domain terms that bias recognition (via the initial prompt) and fix its spelling
+/// afterwards.
+///
+/// File format, one entry per line (`#` at the start of a line or after a space starts
+/// a comment, so `C#` is a term):
+///
+/// ```text
+/// Kubernetes
+/// PostgreSQL = postgres sequel, post gress
+/// ```
+///
+/// Each term and its `=` aliases are matched case-insensitively in the transcript
+/// and replaced with the term as written. A term spelled like an ordinary word ("Go",
+/// "Rust") leaves its all-lowercase form alone ("let's go"), unless that form is
+/// listed as an alias (`Kubernetes = kubernetes`).
+#[derive(Debug, Clone)]
+pub struct Glossary {
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    term: String,
+    pattern: Regex,
+    /// The term's all-lowercase form, when it is left alone
+    plain_word: Option<String>,
+}
+
+impl Glossary {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read glossary {}", path.display()))?;
+        let mut entries = Vec::new();
+        for (n, line) in content.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (term, aliases) = match line.split_once('=') {
+                Some((term, aliases)) => (term.trim(), aliases.split(',').collect()),
+                None => (line, Vec::new()),
+            };
+            if term.is_empty() {
+                return Err(anyhow!(
+                    "{}:{}: missing term before '='",
+                    path.display(),
+                    n + 1
+                ));
+            }
+            let aliases: Vec<&str> = aliases
+                .into_iter()
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .collect();
+            let alternatives: Vec<String> = std::iter::once(term)
+                .chain(aliases.iter().copied())
+                .map(word_pattern)
+                .collect();
+            let pattern = RegexBuilder::new(&alternatives.join("|"))
+                .case_insensitive(true)
+                .build()?;
+            let lowercase = term.to_lowercase();
+            let plain_word = (is_plain_word(term) && !aliases.contains(&lowercase.as_str()))
+                .then_some(lowercase);
+            entries.push(Entry {
+                term: term.to_string(),
+                pattern,
+                plain_word,
+            });
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Append as many terms to `prompt` as fit in whisper's prompt budget. Returns the
+    /// prompt and the number of terms that didn't fit.
+    pub fn pack_prompt(&self, prompt: Option<&str>) -> (String, usize) {
+        let mut out = prompt.unwrap_or("").trim().to_string();
+        let mut used = estimate_tokens(&out);
+        let mut packed = 0;
+        for e in &self.entries {
+            // ", " or the leading space costs about one token
+            let cost = estimate_tokens(&e.term) + 1;
+            if used + cost > PROMPT_TOKEN_BUDGET {
+                break;
+            }
+            out.push_str(if packed == 0 {
+                if out.is_empty() { "" } else { " " }
+            } else {
+                ", "
+            });
+            out.push_str(&e.term);
+            used += cost;
+            packed += 1;
+        }
+        if packed > 0 {
+            out.push('.');
+        }
+        (out, self.entries.len() - packed)
+    }
+
+    /// Rewrite every term/alias occurrence in segment and word texts to the term's spelling.
+    pub fn correct(&self, transcript: &mut Transcript) {
+        for seg in &mut transcript.segments {
+            seg.text = self.correct_text(&seg.text);
+            for w in &mut seg.words {
+                w.text = self.correct_text(&w.text);
+            }
+        }
+    }
+
+    fn correct_text(&self, text: &str) -> String {
+        self.entries.iter().fold(text.to_string(), |acc, e| {
+            e.pattern
+                .replace_all(&acc, |caps: &regex::Captures| {
+                    if e.plain_word.as_deref() == Some(&caps[0]) {
+                        caps[0].to_string()
+                    } else {
+                        e.term.clone()
+                    }
+                })
+                .into_owned()
+        })
+    }
+}
+
+/// One capitalized word of letters ("Go", "Rust"), which may also be a common word.
+fn is_plain_word(term: &str) -> bool {
+    let mut chars = term.chars();
+    chars.next().is_some_and(char::is_uppercase)
+        && chars.all(|c| c.is_alphabetic() && c.is_lowercase())
+}
+
+/// `line` up to a `#` that starts it or follows whitespace.
+fn strip_comment(line: &str) -> &str {
+    let mut prev = None;
+    for (i, c) in line.char_indices() {
+        if c == '#' && prev.is_none_or(char::is_whitespace) {
+            return &line[..i];
+        }
+        prev = Some(c);
+    }
+    line
+}
+
+/// Whole-word regex for `s`; `\b` is only added next to word characters so terms
+/// like "C++" or ".NET" still match.
+fn word_pattern(s: &str) -> String {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let escaped = regex::escape(s).replace(' ', r"\s+");
+    format!(
+        "{}{escaped}{}",
+        if is_word(s.chars().next()) { r"\b" } else { "" },
+        if is_word(s.chars().last()) { r"\b" } else { "" }
+    )
+}
+
+/// Rough BPE token count: about 3 characters per token for names and jargon.
+fn estimate_tokens(s: &str) -> usize {
+    s.chars().count().div_ceil(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+    use std::io::Write;
+
+    fn glossary(text: &str) -> Glossary {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+        Glossary::load(file.path()).unwrap()
+    }
+
+    fn corrected(g: &Glossary, text: &str) -> String {
+        let mut t = Transcript {
+            segments: vec![Segment {
+                text: text.into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        g.correct(&mut t);
+        t.segments.remove(0).text
+    }
+
+    #[test]
+    fn hash_inside_a_term_is_not_a_comment() {
+        let g = glossary("# languages\nC#\nF# # functional\nKubernetes\n");
+        assert_eq!(g.pack_prompt(None).0, "C#, F#, Kubernetes.");
+        assert_eq!(
+            corrected(&g, "written in c# and f#"),
+            "written in C# and F#"
+        );
+    }
+
+    #[test]
+    fn aliases_are_replaced_case_insensitively() {
+        let g = glossary("PostgreSQL = postgres sequel, post gress\n");
+        assert_eq!(
+            corrected(&g, "We use Postgres  Sequel and post gress."),
+            "We use PostgreSQL and PostgreSQL."
+        );
+    }
+
+    #[test]
+    fn terms_with_symbols_match_as_whole_words() {
+        let g = glossary("C++\n.NET\nGo\n");
+        assert_eq!(corrected(&g, "c++ or .net"), "C++ or .NET");
+        assert_eq!(corrected(&g, "a good idea"), "a good idea");
+    }
+
+    #[test]
+    fn common_words_stay_lowercase() {
+        let g = glossary("Go\nRust\nKubernetes = kubernetes, k eights\nGitHub\n");
+        assert_eq!(
+            corrected(&g, "let's go, GO code and rust on the bridge"),
+            "let's go, Go code and rust on the bridge"
+        );
+        assert_eq!(corrected(&g, "RUST"), "Rust");
+        // listed as an alias: rewritten
+        assert_eq!(
+            corrected(&g, "kubernetes and k eights"),
+            "Kubernetes and Kubernetes"
+        );
+        // not a plain word: every spelling is rewritten
+        assert_eq!(corrected(&g, "github and GITHUB"), "GitHub and GitHub");
+    }
+
+    #[test]
+    fn missing_term_is_an_error() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"= alias\n").unwrap();
+        assert!(Glossary::load(file.path()).is_err());
+    }
+
+    #[test]
+    fn prompt_stops_at_the_budget() {
+        let terms: Vec<String> = (0..200).map(|i| format!("Term{i}")).collect();
+        let g = glossary(&terms.join("\n"));
+        let (prompt, dropped) = g.pack_prompt(Some("Intro."));
+        assert!(prompt.starts_with("Intro. Term0, Term1"));
+        assert!(dropped > 0);
+        assert!(estimate_tokens(&prompt) <= PROMPT_TOKEN_BUDGET + 1);
+    }
+}
@@ -1,16 +1,184 @@
-use crate::constants::{CACHE_TTL, HF_REPO_API};
-use anyhow::{Result, anyhow};
+use crate::constants::{HF_DEFAULT_ENDPOINT, HF_DEFAULT_REPO};
+use crate::error::Vid2txtError;
+use crate::report::warning;
+use anyhow::{Context, Result, anyhow};
 use dirs::cache_dir;
 use regex::Regex;
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use sanitize_filename::sanitize;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A Hugging Face (or HF-compatible mirror) model repository to pull ggml files from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelRepo {
+    /// Base URL, e.g. `https://huggingface.co`
+    pub endpoint: String,
+    /// Repo id, e.g. `ggerganov/whisper.cpp`
+    pub id: String,
+    pub revision: String,
+}
+
+impl Default for ModelRepo {
+    fn default() -> Self {
+        Self {
+            endpoint: HF_DEFAULT_ENDPOINT.to_string(),
+            id: HF_DEFAULT_REPO.to_string(),
+            revision: "main".to_string(),
+        }
+    }
+}
+
+impl ModelRepo {
+    /// Parse `owner/name[@revision]` or `https://host[/prefix]/owner/name[@revision]`.
+    pub fn parse(spec: &str, default_endpoint: &str) -> Result<Self> {
+        let spec = spec.trim().trim_end_matches('/');
+        let (spec, revision) = match spec.rsplit_once('@') {
+            Some((s, rev)) if !rev.is_empty() && !rev.contains('/') => (s, rev),
+            _ => (spec, "main"),
+        };
+
+        let (endpoint, id) = if let Some(idx) = spec.find("://") {
+            let parts: Vec<&str> = spec[idx + 3..].split('/').collect();
+            if parts.len() < 3 {
+                return Err(anyhow!(
+                    "Invalid model repo '{spec}': expected <endpoint>/<owner>/<name>"
+                ));
+            }
+            let n = parts.len();
+            let endpoint = format!("{}{}", &spec[..idx + 3], parts[..n - 2].join("/"));
+            (endpoint, format!("{}/{}", parts[n - 2], parts[n - 1]))
+        } else {
+            let parts: Vec<&str> = spec.split('/').collect();
+            if parts.len() != 2 || parts.iter().any(|p| p.is_empty()) {
+                return Err(anyhow!(
+                    "Invalid model repo '{spec}': expected <owner>/<name>"
+                ));
+            }
+            (
+                default_endpoint.trim_end_matches('/').to_string(),
+                spec.to_string(),
+            )
+        };
+
+        Ok(Self {
+            endpoint,
+            id,
+            revision: revision.to_string(),
+        })
+    }
+
+    pub fn api_url(&self) -> String {
+        if self.revision == "main" {
+            format!("{}/api/models/{}?expand=siblings", self.endpoint, self.id)
+        } else {
+            format!(
+                "{}/api/models/{}/revision/{}?expand=siblings",
+                self.endpoint, self.id, self.revision
+            )
+        }
+    }
+
+    pub fn resolve_url(&self, rfilename: &str) -> String {
+        format!(
+            "{}/{}/resolve/{}/{}?download=true",
+            self.endpoint, self.id, self.revision, rfilename
+        )
+    }
+
+    /// The upstream whisper.cpp repo keeps its models directly in `models/`
+    /// (where whisper.cpp's own download script puts them); other repos get a subfolder
+    /// so identically named files don't clash.
+    pub fn is_default(&self) -> bool {
+        self.id == HF_DEFAULT_REPO
+    }
+
+    pub fn local_dir(&self, models_dir: &Path) -> PathBuf {
+        if self.is_default() {
+            models_dir.to_path_buf()
+        } else {
+            models_dir.join(sanitize(self.id.replace('/', "__")))
+        }
+    }
+
+    /// `host[:port]` of the endpoint, lowercased.
+    pub fn host(&self) -> String {
+        host_of(&self.endpoint)
+    }
+
+    fn cache_key(&self) -> String {
+        let host = self
+            .endpoint
+            .split_once("://")
+            .map(|(_, h)| h)
+            .unwrap_or(&self.endpoint);
+        sanitize(format!("{host}_{}_{}", self.id, self.revision).replace(['/', ':'], "_"))
+    }
+}
+
+fn host_of(url: &str) -> String {
+    let rest = url.split_once("://").map(|(_, r)| r).unwrap_or(url);
+    rest.split('/').next().unwrap_or("").to_ascii_lowercase()
+}
+
+/// Bearer tokens by endpoint host. A plain token (`HF_TOKEN`) is only ever sent to
+/// huggingface.co, so mirrors never see it; `HOST=TOKEN` gives another host its own.
+#[derive(Debug, Clone, Default)]
+pub struct HfTokens(Vec<(String, String)>);
+
+impl HfTokens {
+    pub fn parse(specs: &[String]) -> Result<Self> {
+        let mut tokens = Vec::new();
+        for spec in specs.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            // base64 tokens may end in '=', so only a host-like prefix counts
+            let scoped = spec.split_once('=').filter(|(host, _)| {
+                let host = host.to_ascii_lowercase();
+                (host.contains('.') || host.contains(':') || host == "localhost")
+                    && host
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || ".-:".contains(c))
+            });
+            let (host, token) = match scoped {
+                Some((host, token)) => (host.to_ascii_lowercase(), token),
+                None => (host_of(HF_DEFAULT_ENDPOINT), spec),
+            };
+            if token.is_empty() {
+                return Err(anyhow!("--hf-token {spec}: empty token"));
+            }
+            tokens.push((host, token.to_string()));
+        }
+        Ok(Self(tokens))
+    }
+
+    /// The token for `repo`'s endpoint, if any.
+    pub fn for_repo(&self, repo: &ModelRepo) -> Option<&str> {
+        let host = repo.host();
+        self.0
+            .iter()
+            .rev()
+            .find(|(h, _)| *h == host)
+            .map(|(_, t)| t.as_str())
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct HfFile {
     pub rfilename: String,
     #[serde(default)]
     pub size: Option<u64>,
+    /// Repo the file was listed in (not part of the API response)
+    #[serde(skip)]
+    pub source: ModelRepo,
+}
+
+impl HfFile {
+    pub fn local_path(&self, models_dir: &Path) -> PathBuf {
+        self.source.local_dir(models_dir).join(&self.rfilename)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -19,36 +187,250 @@ struct HfModel {
     siblings: Vec<HfFile>,
 }
 
-pub fn cache_file_path() -> Result<PathBuf> {
+/// On-disk cache entry: the API response plus the validators used for conditional
+/// refreshes. Flattened so caches written before validators existed still load.
+#[derive(Debug, Deserialize, Serialize)]
+struct CachedCatalog {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    #[serde(flatten)]
+    model: HfModel,
+}
+
+/// Summary of a cached model list, for `vid2txt cache status`.
+pub struct CatalogCacheInfo {
+    pub models: usize,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Where and how to fetch the model catalog from.
+#[derive(Debug, Clone)]
+pub struct CatalogOptions {
+    pub repos: Vec<ModelRepo>,
+    /// Bearer tokens for gated/private repos
+    pub tokens: HfTokens,
+    /// Where model lists are cached ([`cache_dir_path`])
+    pub cache_dir: PathBuf,
+    pub refresh: bool,
+    pub offline: bool,
+    pub prefer_quantized: bool,
+    /// How long a cached model list is used without revalidating it
+    pub ttl: Duration,
+}
+
+pub fn cache_dir_path() -> Result<PathBuf> {
     let base = cache_dir().ok_or_else(|| anyhow!("Cannot determine cache directory"))?;
-    Ok(base.join("vid2txt").join("models.json"))
-}
-
-pub fn fetch_hf_files_cached(refresh: bool, prefer_quantized: bool) -> Result<Vec<HfFile>> {
-    let path = cache_file_path()?;
-    if !refresh {
-        if let Ok(meta) = fs::metadata(&path) {
-            if let Ok(modified) = meta.modified() {
-                if modified.elapsed().unwrap_or_else(|_| CACHE_TTL * 2) < CACHE_TTL {
-                    if let Ok(bytes) = fs::read(&path) {
-                        if let Ok(model) = serde_json::from_slice::<HfModel>(&bytes) {
-                            return Ok(filter_and_sort_files(model.siblings, prefer_quantized));
-                        }
-                    }
+    Ok(base.join("vid2txt"))
+}
+
+pub fn cache_file_path(cache_dir: &Path, repo: &ModelRepo) -> PathBuf {
+    cache_dir.join(format!("models-{}.json", repo.cache_key()))
+}
+
+/// Versions before multi-repo support cached the default repo's list as
+/// `models.json`; move it to that repo's cache file (or drop it if that exists).
+fn migrate_legacy_cache(cache_dir: &Path) {
+    let legacy = cache_dir.join("models.json");
+    if !legacy.is_file() {
+        return;
+    }
+    let current = cache_file_path(cache_dir, &ModelRepo::default());
+    if current.exists() || fs::rename(&legacy, &current).is_err() {
+        let _ = fs::remove_file(&legacy);
+    }
+}
+
+fn authed_get(url: &str, token: Option<&str>) -> RequestBuilder {
+    let req = Client::new().get(url);
+    match token {
+        Some(t) => req.bearer_auth(t),
+        None => req,
+    }
+}
+
+/// GET `url`, sending `token` as a bearer token when present.
+pub fn http_get(url: &str, token: Option<&str>) -> Result<Response> {
+    authed_get(url, token)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .context(Vid2txtError::Network(format!("Request to {url} failed")))
+}
+
+/// Load the model list of every configured repo, honouring the cache TTL.
+///
+/// With `offline`, only the cache is consulted (regardless of its age). When a
+/// network refresh fails and a stale cache exists, the stale list is used instead.
+/// A repo that can't be loaded at all is skipped with a warning, unless none can.
+pub fn fetch_hf_files_cached(opts: &CatalogOptions) -> Result<Vec<HfFile>> {
+    migrate_legacy_cache(&opts.cache_dir);
+    let mut all = Vec::new();
+    let mut first_err = None;
+    for repo in &opts.repos {
+        match fetch_repo_files_cached(repo, opts) {
+            Ok(files) => all.extend(files),
+            Err(e) => {
+                if opts.repos.len() > 1 {
+                    warning!("⚠️  Skipping model repo {}: {e:#}", repo.id);
                 }
+                first_err.get_or_insert(e);
             }
         }
     }
+    match first_err {
+        Some(e) if all.is_empty() => Err(e),
+        _ => Ok(filter_and_sort_files(all, opts.prefer_quantized)),
+    }
+}
+
+fn fetch_repo_files_cached(repo: &ModelRepo, opts: &CatalogOptions) -> Result<Vec<HfFile>> {
+    let path = cache_file_path(&opts.cache_dir, repo);
+    let cached = read_cache(&path);
+    let with_source = |model: HfModel| -> Vec<HfFile> {
+        model
+            .siblings
+            .into_iter()
+            .map(|f| HfFile {
+                source: repo.clone(),
+                ..f
+            })
+            .collect()
+    };
+
+    if opts.offline {
+        let (entry, _) = cached.ok_or_else(|| {
+            Vid2txtError::Network(format!(
+                "--offline: no cached model list at {} (run once with network access first)",
+                path.display()
+            ))
+        })?;
+        return Ok(with_source(entry.model));
+    }
 
-    let resp = reqwest::blocking::get(HF_REPO_API)?.error_for_status()?;
-    let model: HfModel = resp.json()?;
+    let cached = match cached {
+        Some((entry, age)) if !opts.refresh && age < opts.ttl => {
+            return Ok(with_source(entry.model));
+        }
+        c => c.map(|(entry, _)| entry),
+    };
 
+    match fetch_remote(repo, opts.tokens.for_repo(repo), cached.as_ref()) {
+        // 304: the cached list is current; rewrite it to restart the TTL
+        Ok(None) => {
+            let entry = cached.expect("conditional request without cache");
+            write_cache(&path, &entry)?;
+            Ok(with_source(entry.model))
+        }
+        Ok(Some(entry)) => {
+            write_cache(&path, &entry)?;
+            Ok(with_source(entry.model))
+        }
+        Err(e) => match cached {
+            Some(entry) => {
+                warning!(
+                    "⚠️  Could not refresh model list for {} ({e:#}); using stale cache at {}",
+                    repo.id,
+                    path.display()
+                );
+                Ok(with_source(entry.model))
+            }
+            None => Err(e),
+        },
+    }
+}
+
+fn read_cache(path: &Path) -> Option<(CachedCatalog, Duration)> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let age = modified.elapsed().unwrap_or(Duration::MAX);
+    let bytes = fs::read(path).ok()?;
+    let entry = serde_json::from_slice::<CachedCatalog>(&bytes).ok()?;
+    Some((entry, age))
+}
+
+fn write_cache(path: &Path, entry: &CachedCatalog) -> Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
-    fs::write(&path, serde_json::to_vec(&model)?)?;
+    fs::write(path, serde_json::to_vec(entry)?)?;
+    Ok(())
+}
 
-    Ok(filter_and_sort_files(model.siblings, prefer_quantized))
+/// Fetch a repo's file list. With a cached entry, the request is conditional and
+/// `Ok(None)` means the cache is still current.
+fn fetch_remote(
+    repo: &ModelRepo,
+    token: Option<&str>,
+    cached: Option<&CachedCatalog>,
+) -> Result<Option<CachedCatalog>> {
+    let mut req = authed_get(&repo.api_url(), token);
+    if let Some(c) = cached {
+        if let Some(etag) = &c.etag {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(lm) = &c.last_modified {
+            req = req.header(IF_MODIFIED_SINCE, lm);
+        }
+    }
+    let resp = req
+        .send()
+        .and_then(|r| r.error_for_status())
+        .with_context(|| {
+            Vid2txtError::Network(format!("Failed to fetch model list from {}", repo.endpoint))
+        })?;
+    if resp.status() == StatusCode::NOT_MODIFIED && cached.is_some() {
+        return Ok(None);
+    }
+
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    Ok(Some(CachedCatalog {
+        etag,
+        last_modified,
+        model: resp.json()?,
+    }))
+}
+
+/// Parse a cached model list written by [`fetch_hf_files_cached`].
+pub fn catalog_cache_info(path: &Path) -> Option<CatalogCacheInfo> {
+    let (entry, _) = read_cache(path)?;
+    Some(CatalogCacheInfo {
+        models: filter_and_sort_files(entry.model.siblings, false).len(),
+        etag: entry.etag,
+        last_modified: entry.last_modified,
+    })
+}
+
+/// Model files already present in `models_dir`, as a stand-in catalog when offline
+/// without a cached model list.
+pub fn local_model_files(
+    models_dir: &Path,
+    repos: &[ModelRepo],
+    prefer_quantized: bool,
+) -> Vec<HfFile> {
+    let mut files = Vec::new();
+    for repo in repos {
+        let Ok(rd) = fs::read_dir(repo.local_dir(models_dir)) else {
+            continue;
+        };
+        files.extend(
+            rd.filter_map(|e| e.ok())
+                .filter(|e| e.path().is_file())
+                .map(|e| HfFile {
+                    rfilename: e.file_name().to_string_lossy().into_owned(),
+                    size: e.metadata().ok().map(|m| m.len()),
+                    source: repo.clone(),
+                }),
+        );
+    }
+    filter_and_sort_files(files, prefer_quantized)
 }
 
 pub fn filter_and_sort_files(files: Vec<HfFile>, prefer_quantized: bool) -> Vec<HfFile> {
@@ -80,3 +462,281 @@ pub fn is_quantized_name(name: &str) -> bool {
     // rough heuristics for whisper.cpp repo
     name.contains("-q") || name.contains(".q") || name.contains("-q5") || name.contains("-q8")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::MockServer;
+
+    fn repo(spec: &str) -> ModelRepo {
+        ModelRepo::parse(spec, HF_DEFAULT_ENDPOINT).unwrap()
+    }
+
+    #[test]
+    fn parses_short_repo_ids() {
+        let r = repo("ggerganov/whisper.cpp");
+        assert_eq!(r, ModelRepo::default());
+        assert!(r.is_default());
+
+        let r = repo(" someone/models@v2 ");
+        assert_eq!(r.endpoint, "https://huggingface.co");
+        assert_eq!(r.id, "someone/models");
+        assert_eq!(r.revision, "v2");
+
+        let r = ModelRepo::parse("a/b", "https://hf-mirror.com/").unwrap();
+        assert_eq!(r.endpoint, "https://hf-mirror.com");
+    }
+
+    #[test]
+    fn parses_full_urls() {
+        let r = repo("https://mirror.example.org/hf/someone/models@dev/");
+        assert_eq!(r.endpoint, "https://mirror.example.org/hf");
+        assert_eq!(r.id, "someone/models");
+        assert_eq!(r.revision, "dev");
+        assert_eq!(r.host(), "mirror.example.org");
+
+        let r = repo("http://LOCALHOST:8080/a/b");
+        assert_eq!(r.endpoint, "http://LOCALHOST:8080");
+        assert_eq!(r.revision, "main");
+        assert_eq!(r.host(), "localhost:8080");
+    }
+
+    #[test]
+    fn rejects_malformed_repos() {
+        for spec in ["whisper.cpp", "a/b/c", "/b", "https://host/only-one"] {
+            assert!(
+                ModelRepo::parse(spec, HF_DEFAULT_ENDPOINT).is_err(),
+                "{spec}"
+            );
+        }
+    }
+
+    #[test]
+    fn builds_urls() {
+        let r = repo("a/b");
+        assert_eq!(
+            r.api_url(),
+            "https://huggingface.co/api/models/a/b?expand=siblings"
+        );
+        assert_eq!(
+            r.resolve_url("ggml-base.bin"),
+            "https://huggingface.co/a/b/resolve/main/ggml-base.bin?download=true"
+        );
+        let r = repo("https://m.example/x/a/b@v1");
+        assert_eq!(
+            r.api_url(),
+            "https://m.example/x/api/models/a/b/revision/v1?expand=siblings"
+        );
+        assert_eq!(
+            r.resolve_url("ggml-tiny.bin"),
+            "https://m.example/x/a/b/resolve/v1/ggml-tiny.bin?download=true"
+        );
+    }
+
+    #[test]
+    fn local_dirs_keep_the_default_repo_flat() {
+        let models = Path::new("/m");
+        assert_eq!(ModelRepo::default().local_dir(models), models);
+        assert_eq!(repo("a/b").local_dir(models), models.join("a__b"));
+    }
+
+    #[test]
+    fn plain_tokens_only_go_to_huggingface() {
+        let tokens = HfTokens::parse(&["hf_secret".into()]).unwrap();
+        assert_eq!(tokens.for_repo(&repo("a/b")), Some("hf_secret"));
+        assert_eq!(tokens.for_repo(&repo("https://hf-mirror.com/a/b")), None);
+        assert_eq!(tokens.for_repo(&repo("http://127.0.0.1:9/a/b")), None);
+    }
+
+    #[test]
+    fn scoped_tokens_go_to_their_host() {
+        let tokens = HfTokens::parse(&[
+            "hf_secret".into(),
+            "HF-Mirror.com=mirror_secret".into(),
+            "localhost:8080=local".into(),
+            "abc==".into(),
+        ])
+        .unwrap();
+        assert_eq!(
+            tokens.for_repo(&repo("https://hf-mirror.com/a/b")),
+            Some("mirror_secret")
+        );
+        assert_eq!(
+            tokens.for_repo(&repo("http://localhost:8080/a/b")),
+            Some("local")
+        );
+        // not host-like: a plain token that happens to end in '='
+        assert_eq!(tokens.for_repo(&repo("a/b")), Some("abc=="));
+        assert!(HfTokens::parse(&["example.com=".into()]).is_err());
+    }
+
+    fn options(cache_dir: &Path, repos: Vec<ModelRepo>, tokens: HfTokens) -> CatalogOptions {
+        CatalogOptions {
+            repos,
+            tokens,
+            cache_dir: cache_dir.to_path_buf(),
+            refresh: false,
+            offline: false,
+            prefer_quantized: false,
+            ttl: Duration::from_secs(3600),
+        }
+    }
+
+    fn names(files: &[HfFile]) -> Vec<(&str, &str)> {
+        files
+            .iter()
+            .map(|f| (f.source.id.as_str(), f.rfilename.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn merges_repos_and_skips_failing_ones() {
+        let server = MockServer::start(|req| {
+            let body = match req.path.as_str() {
+                "/api/models/one/models?expand=siblings" => {
+                    r#"{"siblings":[{"rfilename":"ggml-small.bin"},{"rfilename":"README.md"}]}"#
+                }
+                "/api/models/two/models/revision/v2?expand=siblings" => {
+                    r#"{"siblings":[{"rfilename":"ggml-base-q5_0.bin","size":5},{"rfilename":"ggml-base.bin"}]}"#
+                }
+                _ => return (404, "text/plain", "no such repo".into()),
+            };
+            (200, "application/json", body.into())
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let repos = ["one/models", "missing/models", "two/models@v2"]
+            .iter()
+            .map(|s| ModelRepo::parse(s, &server.url).unwrap())
+            .collect();
+        let tokens = HfTokens::parse(&["hf_secret".into()]).unwrap();
+        let opts = options(dir.path(), repos, tokens);
+
+        let files = fetch_hf_files_cached(&opts).unwrap();
+        assert_eq!(
+            names(&files),
+            [
+                ("two/models", "ggml-base.bin"),
+                ("one/models", "ggml-small.bin"),
+                ("two/models", "ggml-base-q5_0.bin"),
+            ]
+        );
+        assert_eq!(files[2].size, Some(5));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        // a huggingface.co token never reaches another host
+        assert!(requests.iter().all(|r| r.header("authorization").is_none()));
+
+        // now served from the cache
+        let files = fetch_hf_files_cached(&opts).unwrap();
+        assert_eq!(files.len(), 3);
+        assert_eq!(
+            server.requests().len(),
+            4,
+            "only the failing repo is retried"
+        );
+    }
+
+    #[test]
+    fn sends_scoped_tokens_and_fails_when_no_repo_loads() {
+        let server = MockServer::start(|_| (401, "text/plain", "unauthorized".into()));
+        let dir = tempfile::tempdir().unwrap();
+        let repo = ModelRepo::parse("a/b", &server.url).unwrap();
+        let spec = format!("{}=scoped", repo.host());
+        let opts = options(dir.path(), vec![repo], HfTokens::parse(&[spec]).unwrap());
+
+        let err = fetch_hf_files_cached(&opts).unwrap_err();
+        assert!(format!("{err:#}").contains("Failed to fetch model list"));
+        assert_eq!(
+            server.requests()[0].header("authorization"),
+            Some("Bearer scoped")
+        );
+    }
+
+    #[test]
+    fn revalidates_the_cache_with_conditional_requests() {
+        const ETAG: &str = "\"v1\"";
+        const LAST_MODIFIED: &str = "Tue, 01 Sep 2026 10:00:00 GMT";
+        let server = MockServer::start_with(|req| {
+            if req.header("if-none-match") == Some(ETAG) {
+                return (304, Vec::new(), String::new());
+            }
+            let headers = vec![
+                ("Content-Type", "application/json".to_string()),
+                ("ETag", ETAG.to_string()),
+                ("Last-Modified", LAST_MODIFIED.to_string()),
+            ];
+            let body = r#"{"siblings":[{"rfilename":"ggml-base.bin"}]}"#;
+            (200, headers, body.to_string())
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let repo = ModelRepo::parse("a/b", &server.url).unwrap();
+        let path = cache_file_path(dir.path(), &repo);
+        let mut opts = options(dir.path(), vec![repo], HfTokens::default());
+        opts.ttl = Duration::from_secs(60);
+
+        assert_eq!(fetch_hf_files_cached(&opts).unwrap().len(), 1);
+        let (entry, _) = read_cache(&path).unwrap();
+        assert_eq!(entry.etag.as_deref(), Some(ETAG));
+        assert_eq!(entry.last_modified.as_deref(), Some(LAST_MODIFIED));
+
+        // past the TTL: revalidate, and the 304 keeps the cached list
+        let old = std::time::SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        assert_eq!(fetch_hf_files_cached(&opts).unwrap().len(), 1);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("if-none-match"), None);
+        assert_eq!(requests[1].header("if-none-match"), Some(ETAG));
+        assert_eq!(requests[1].header("if-modified-since"), Some(LAST_MODIFIED));
+        // the TTL restarted, the validators are kept
+        let (entry, age) = read_cache(&path).unwrap();
+        assert!(age < Duration::from_secs(60), "{age:?}");
+        assert_eq!(entry.etag.as_deref(), Some(ETAG));
+        assert_eq!(fetch_hf_files_cached(&opts).unwrap().len(), 1);
+        assert_eq!(
+            server.requests().len(),
+            2,
+            "fresh cache served without a request"
+        );
+
+        // --refresh-models revalidates even a fresh cache
+        opts.refresh = true;
+        assert_eq!(fetch_hf_files_cached(&opts).unwrap().len(), 1);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn offline_requires_a_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut opts = options(dir.path(), vec![repo("a/b")], HfTokens::default());
+        opts.offline = true;
+        assert!(fetch_hf_files_cached(&opts).is_err());
+    }
+
+    #[test]
+    fn migrates_the_legacy_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("models.json"),
+            r#"{"siblings":[{"rfilename":"ggml-tiny.bin"}]}"#,
+        )
+        .unwrap();
+        let mut opts = options(dir.path(), vec![ModelRepo::default()], HfTokens::default());
+        opts.offline = true;
+
+        let files = fetch_hf_files_cached(&opts).unwrap();
+        assert_eq!(names(&files), [(HF_DEFAULT_REPO, "ggml-tiny.bin")]);
+        assert!(!dir.path().join("models.json").exists());
+        assert!(cache_file_path(dir.path(), &ModelRepo::default()).exists());
+
+        // an up-to-date cache wins over a stray legacy file
+        fs::write(dir.path().join("models.json"), "{}").unwrap();
+        assert_eq!(fetch_hf_files_cached(&opts).unwrap().len(), 1);
+        assert!(!dir.path().join("models.json").exists());
+    }
+}
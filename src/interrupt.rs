@@ -0,0 +1,251 @@
+//! Ctrl-C handling. The first Ctrl-C stops the running tool (its whole process
+//! group, so yt-dlp's own ffmpeg goes too) and the current input fails as cancelled,
+//! removing the files it had only half written. A second Ctrl-C kills everything and
+//! exits at once.
+
+use crate::error::Vid2txtError;
+use crate::logging;
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+/// How often a wait in [`interruptible`] looks for Ctrl-C.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+struct Active {
+    /// Process (group) ids of running tools
+    children: Vec<u32>,
+    /// Files and directories still being written
+    partial: Vec<PathBuf>,
+}
+
+static ACTIVE: Mutex<Active> = Mutex::new(Active {
+    children: Vec::new(),
+    partial: Vec::new(),
+});
+
+fn active() -> std::sync::MutexGuard<'static, Active> {
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn install() -> Result<()> {
+    ctrlc::set_handler(on_interrupt).context("Failed to install the Ctrl-C handler")
+}
+
+fn on_interrupt() {
+    if INTERRUPTS.fetch_add(1, Ordering::SeqCst) == 0 {
+        logging::warn("⏹️  Interrupted; stopping (press Ctrl-C again to force quit)");
+        signal_children(false);
+        return;
+    }
+    signal_children(true);
+    let partial = std::mem::take(&mut active().partial);
+    for path in &partial {
+        remove(path);
+    }
+    logging::warn("⏹️  Force quit");
+    std::process::exit(130);
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTS.load(Ordering::SeqCst) > 0
+}
+
+/// `Err(Cancelled)` once Ctrl-C was pressed; call between steps.
+pub fn check() -> Result<()> {
+    if interrupted() {
+        return Err(Vid2txtError::Cancelled.into());
+    }
+    Ok(())
+}
+
+/// Run the blocking `work` (an HTTP request) on a worker thread so Ctrl-C can cancel
+/// it: `Err(Cancelled)` right away, leaving the request to finish or fail unobserved.
+pub fn interruptible<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> Result<T> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(work());
+    });
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(result) => return Ok(result),
+            Err(RecvTimeoutError::Timeout) => check()?,
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(anyhow!("The request thread panicked"));
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn signal_children(force: bool) {
+    let sig = if force { libc::SIGKILL } else { libc::SIGTERM };
+    for pid in &active().children {
+        // SAFETY: kill(2) has no memory-safety preconditions
+        unsafe {
+            libc::kill(-(*pid as libc::pid_t), sig);
+        }
+    }
+}
+
+/// The console delivers Ctrl-C to every attached process, tools included.
+#[cfg(not(unix))]
+fn signal_children(_force: bool) {}
+
+/// A tool started by [`spawn`]; no longer signalled once dropped.
+pub struct ChildGuard(u32);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        active().children.retain(|pid| *pid != self.0);
+    }
+}
+
+/// Spawn `cmd` in its own process group, which Ctrl-C stops as a whole. Keep the
+/// guard until the child has been waited for.
+pub fn spawn(cmd: &mut Command) -> io::Result<(Child, ChildGuard)> {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(cmd, 0);
+    let child = cmd.spawn()?;
+    let pid = child.id();
+    active().children.push(pid);
+    // pressed while it was starting
+    if interrupted() {
+        signal_children(false);
+    }
+    Ok((child, ChildGuard(pid)))
+}
+
+/// A file or directory about to be written: removed when dropped, unless
+/// [`Partial::done`] was called, and by a forced quit. Only ever removes what vid2txt
+/// created: a path that already exists is not tracked.
+pub struct Partial {
+    path: Option<PathBuf>,
+    /// Say so when removing it (not for scratch directories)
+    announce: bool,
+}
+
+impl Partial {
+    pub fn new(path: &Path) -> Self {
+        if path.exists() {
+            return Self {
+                path: None,
+                announce: false,
+            };
+        }
+        Self::track(path, true)
+    }
+
+    /// A temporary directory just created by a `TempDir`, which removes it anyway
+    /// except on a forced quit.
+    pub fn scratch(path: &Path) -> Self {
+        Self::track(path, false)
+    }
+
+    fn track(path: &Path, announce: bool) -> Self {
+        active().partial.push(path.to_path_buf());
+        Self {
+            path: Some(path.to_path_buf()),
+            announce,
+        }
+    }
+
+    /// The artifact is complete; keep it.
+    pub fn done(mut self) {
+        if let Some(path) = self.path.take() {
+            active().partial.retain(|p| *p != path);
+        }
+    }
+}
+
+impl Drop for Partial {
+    fn drop(&mut self) {
+        let Some(path) = self.path.take() else {
+            return;
+        };
+        active().partial.retain(|p| *p != path);
+        if path.exists() {
+            remove(&path);
+            if self.announce {
+                logging::warn(&format!("🧹 Removed incomplete {}", path.display()));
+            }
+        }
+    }
+}
+
+fn remove(path: &Path) {
+    let _ = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracked(path: &Path) -> bool {
+        active().partial.iter().any(|p| p == path)
+    }
+
+    #[test]
+    fn partial_files_are_removed_unless_done() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("talk.wav");
+        let partial = Partial::new(&wav);
+        fs::write(&wav, b"half").unwrap();
+        assert!(tracked(&wav));
+        drop(partial);
+        assert!(!wav.exists());
+        assert!(!tracked(&wav));
+
+        let txt = dir.path().join("talk.txt");
+        let partial = Partial::new(&txt);
+        fs::write(&txt, b"all").unwrap();
+        partial.done();
+        assert!(txt.exists());
+        assert!(!tracked(&txt));
+    }
+
+    #[test]
+    fn partial_directories_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let models = dir.path().join("models");
+        let partial = Partial::new(&models);
+        fs::create_dir(&models).unwrap();
+        fs::write(models.join("ggml-base.bin.part"), b"half").unwrap();
+        drop(partial);
+        assert!(!models.exists());
+    }
+
+    #[test]
+    fn existing_files_are_never_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let txt = dir.path().join("talk.txt");
+        fs::write(&txt, b"from an earlier run").unwrap();
+        drop(Partial::new(&txt));
+        assert_eq!(fs::read(&txt).unwrap(), b"from an earlier run");
+        assert!(!tracked(&txt));
+    }
+
+    #[test]
+    fn interruptible_returns_the_result() {
+        assert_eq!(interruptible(|| 42).unwrap(), 42);
+        let slow = interruptible(|| {
+            thread::sleep(POLL_INTERVAL * 2);
+            "done"
+        });
+        assert_eq!(slow.unwrap(), "done");
+        assert!(interruptible(|| -> u8 { panic!("request failed") }).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+/// Languages whisper knows, as (code, English name), in whisper's token order.
+const LANGUAGES: &[(&str, &str)] = &[
+    ("en", "english"),
+    ("zh", "chinese"),
+    ("de", "german"),
+    ("es", "spanish"),
+    ("ru", "russian"),
+    ("ko", "korean"),
+    ("fr", "french"),
+    ("ja", "japanese"),
+    ("pt", "portuguese"),
+    ("tr", "turkish"),
+    ("pl", "polish"),
+    ("ca", "catalan"),
+    ("nl", "dutch"),
+    ("ar", "arabic"),
+    ("sv", "swedish"),
+    ("it", "italian"),
+    ("id", "indonesian"),
+    ("hi", "hindi"),
+    ("fi", "finnish"),
+    ("vi", "vietnamese"),
+    ("he", "hebrew"),
+    ("uk", "ukrainian"),
+    ("el", "greek"),
+    ("ms", "malay"),
+    ("cs", "czech"),
+    ("ro", "romanian"),
+    ("da", "danish"),
+    ("hu", "hungarian"),
+    ("ta", "tamil"),
+    ("no", "norwegian"),
+    ("th", "thai"),
+    ("ur", "urdu"),
+    ("hr", "croatian"),
+    ("bg", "bulgarian"),
+    ("lt", "lithuanian"),
+    ("la", "latin"),
+    ("mi", "maori"),
+    ("ml", "malayalam"),
+    ("cy", "welsh"),
+    ("sk", "slovak"),
+    ("te", "telugu"),
+    ("fa", "persian"),
+    ("lv", "latvian"),
+    ("bn", "bengali"),
+    ("sr", "serbian"),
+    ("az", "azerbaijani"),
+    ("sl", "slovenian"),
+    ("kn", "kannada"),
+    ("et", "estonian"),
+    ("mk", "macedonian"),
+    ("br", "breton"),
+    ("eu", "basque"),
+    ("is", "icelandic"),
+    ("hy", "armenian"),
+    ("ne", "nepali"),
+    ("mn", "mongolian"),
+    ("bs", "bosnian"),
+    ("kk", "kazakh"),
+    ("sq", "albanian"),
+    ("sw", "swahili"),
+    ("gl", "galician"),
+    ("mr", "marathi"),
+    ("pa", "punjabi"),
+    ("si", "sinhala"),
+    ("km", "khmer"),
+    ("sn", "shona"),
+    ("yo", "yoruba"),
+    ("so", "somali"),
+    ("af", "afrikaans"),
+    ("oc", "occitan"),
+    ("ka", "georgian"),
+    ("be", "belarusian"),
+    ("tg", "tajik"),
+    ("sd", "sindhi"),
+    ("gu", "gujarati"),
+    ("am", "amharic"),
+    ("yi", "yiddish"),
+    ("lo", "lao"),
+    ("uz", "uzbek"),
+    ("fo", "faroese"),
+    ("ht", "haitian creole"),
+    ("ps", "pashto"),
+    ("tk", "turkmen"),
+    ("nn", "nynorsk"),
+    ("mt", "maltese"),
+    ("sa", "sanskrit"),
+    ("lb", "luxembourgish"),
+    ("my", "myanmar"),
+    ("bo", "tibetan"),
+    ("tl", "tagalog"),
+    ("mg", "malagasy"),
+    ("as", "assamese"),
+    ("tt", "tatar"),
+    ("haw", "hawaiian"),
+    ("ln", "lingala"),
+    ("ha", "hausa"),
+    ("ba", "bashkir"),
+    ("jw", "javanese"),
+    ("su", "sundanese"),
+    ("yue", "cantonese"),
+];
+
+/// Normalize a language code or English name ("German", "de") to whisper's code.
+pub fn to_code(language: &str) -> Option<&'static str> {
+    let l = language.trim().to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(code, name)| *code == l || *name == l)
+        .map(|(code, _)| *code)
+}
+
+/// clap value parser for `--language`: a known code/name, or "auto".
+pub fn parse_language_or_auto(s: &str) -> Result<String, String> {
+    if s.eq_ignore_ascii_case("auto") {
+        return Ok("auto".into());
+    }
+    parse_language(s)
+}
+
+/// clap value parser for a known language code or name; yields the code.
+pub fn parse_language(s: &str) -> Result<String, String> {
+    to_code(s)
+        .map(str::to_string)
+        .ok_or_else(|| format!("unknown language '{s}' (use a code like en, de, pt)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_names_and_codes() {
+        for (input, code) in [
+            ("de", Some("de")),
+            ("German", Some("de")),
+            (" PT ", Some("pt")),
+            ("haitian creole", Some("ht")),
+            ("Cantonese", Some("yue")),
+            ("deutsch", None),
+            ("", None),
+        ] {
+            assert_eq!(to_code(input), code, "{input}");
+        }
+    }
+
+    #[test]
+    fn parses_language_arguments() {
+        assert_eq!(parse_language("Japanese").as_deref(), Ok("ja"));
+        assert!(parse_language("klingon").unwrap_err().contains("klingon"));
+        assert!(parse_language("auto").is_err());
+        assert_eq!(parse_language_or_auto("AUTO").as_deref(), Ok("auto"));
+        assert_eq!(parse_language_or_auto("english").as_deref(), Ok("en"));
+    }
+}
@@ -0,0 +1,195 @@
+//! Console verbosity (`-q`, `-v`, `-vv`) and the `--log-file`, which records every
+//! message plus the command line and full stdout/stderr of every tool vid2txt runs,
+//! timestamped. In a batch each input is also logged to its own job file.
+
+use crate::clock;
+use crate::report::{self, Mode};
+use crate::tools::command_line;
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// `-q`: warnings and errors only, no progress bars
+    Quiet,
+    Normal,
+    /// `-v`: tool output passes through
+    Verbose,
+    /// `-vv`: also the command lines, every line timestamped
+    Debug,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Normal as u8);
+
+struct Files {
+    /// `--log-file` and its path
+    run: Option<(File, PathBuf)>,
+    /// The current input's job log (batches only)
+    job: Option<File>,
+}
+
+static FILES: Mutex<Files> = Mutex::new(Files {
+    run: None,
+    job: None,
+});
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> Level {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => Level::Quiet,
+        1 => Level::Normal,
+        2 => Level::Verbose,
+        _ => Level::Debug,
+    }
+}
+
+/// Start writing the run log to `path` (truncating it).
+pub fn open(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create log file {}", path.display()))?;
+    files().run = Some((file, path.to_path_buf()));
+    let args: Vec<String> = std::env::args().collect();
+    record(
+        "INFO",
+        &format!(
+            "vid2txt {} started: {}",
+            env!("CARGO_PKG_VERSION"),
+            args.join(" ")
+        ),
+    );
+    Ok(())
+}
+
+pub fn file_enabled() -> bool {
+    files().run.is_some()
+}
+
+/// Also log to a job file for the `index`th input (1-based): `run.log` → `run.2.log`.
+/// `None` without `--log-file`.
+pub fn start_job(index: usize, input: &str) -> Result<Option<PathBuf>> {
+    let Some(run_path) = files().run.as_ref().map(|(_, p)| p.clone()) else {
+        return Ok(None);
+    };
+    let stem = run_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match run_path.extension() {
+        Some(ext) => format!("{stem}.{index}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{index}"),
+    };
+    let path = run_path.with_file_name(name);
+    let file = File::create(&path)
+        .with_context(|| format!("Failed to create log file {}", path.display()))?;
+    files().job = Some(file);
+    record(
+        "INFO",
+        &format!("input {index}: {input} (job log {})", path.display()),
+    );
+    Ok(Some(path))
+}
+
+pub fn end_job() {
+    files().job = None;
+}
+
+fn files() -> std::sync::MutexGuard<'static, Files> {
+    FILES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Append `text` to the log files only, one timestamped line per line of text.
+pub fn record(tag: &str, text: &str) {
+    let mut files = files();
+    if files.run.is_none() {
+        return;
+    }
+    let ts = clock::timestamp();
+    let Files { run, job } = &mut *files;
+    for file in run.iter_mut().map(|(f, _)| f).chain(job.iter_mut()) {
+        for line in text.lines() {
+            let _ = writeln!(file, "{ts} {tag} {line}");
+        }
+    }
+}
+
+/// `-vv` prefixes console lines with the time.
+pub fn console_prefix() -> String {
+    if level() >= Level::Debug {
+        format!("[{}] ", &clock::timestamp()[11..23])
+    } else {
+        String::new()
+    }
+}
+
+/// A progress message (see `say!`): stdout, or stderr when stdout carries JSON;
+/// hidden with `-q`.
+pub fn info(text: &str) {
+    record("INFO", text);
+    if level() == Level::Quiet {
+        return;
+    }
+    if report::mode() == Mode::Human {
+        println!("{}{text}", console_prefix());
+    } else {
+        eprintln!("{}{text}", console_prefix());
+    }
+}
+
+/// A warning (see `warning!`): always shown, on stderr.
+pub fn warn(text: &str) {
+    record("WARN", text);
+    eprintln!("{}{text}", console_prefix());
+}
+
+/// About to run `cmd`; echoed with `-vv`.
+pub fn command(cmd: &Command) {
+    let line = command_line(cmd);
+    record("CMD", &format!("$ {line}"));
+    if level() >= Level::Debug {
+        eprintln!("{}$ {line}", console_prefix());
+    }
+}
+
+/// One line of a tool's output, for the log files.
+pub fn child_line(tool: &str, is_stderr: bool, line: &str) {
+    let stream = if is_stderr { "stderr" } else { "stdout" };
+    record("OUT", &format!("[{tool} {stream}] {line}"));
+}
+
+pub fn child_exit(tool: &str, status: &ExitStatus) {
+    record("CMD", &format!("{tool} exited: {status}"));
+}
+
+/// `cmd.output()`, logged like [`crate::progress::run_with_progress`] runs.
+pub fn output(cmd: &mut Command) -> io::Result<Output> {
+    command(cmd);
+    let tool = tool_name(cmd);
+    let out = cmd.output()?;
+    for (is_stderr, bytes) in [(false, &out.stdout), (true, &out.stderr)] {
+        for line in String::from_utf8_lossy(bytes).lines() {
+            child_line(&tool, is_stderr, line);
+        }
+    }
+    child_exit(&tool, &out.status);
+    Ok(out)
+}
+
+/// The program's file name, e.g. `ffmpeg`.
+pub fn tool_name(cmd: &Command) -> String {
+    Path::new(cmd.get_program())
+        .file_name()
+        .unwrap_or(cmd.get_program())
+        .to_string_lossy()
+        .to_string()
+}
@@ -1,13 +1,39 @@
-use anyhow::Result;
+use std::process::ExitCode;
 
 mod app;
+mod backend;
+mod cache;
 mod cli;
-mod cmd;
+mod clock;
+mod config;
 mod constants;
+mod doctor;
+mod error;
+mod filter;
 mod fs_utils;
+mod glossary;
 mod hf;
+mod interrupt;
+mod language;
+mod logging;
+mod media;
+#[cfg(test)]
+mod mock_server;
 mod models;
+mod naming;
+mod progress;
+mod recommend;
+mod report;
+mod tools;
+mod transcript;
 
-fn main() -> Result<()> {
-    app::run()
+fn main() -> ExitCode {
+    match app::run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            logging::record("ERROR", &format!("{e:#}"));
+            eprintln!("Error: {e:?}");
+            ExitCode::from(error::classify(&e).1)
+        }
+    }
 }
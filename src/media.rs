@@ -0,0 +1,284 @@
+use crate::logging;
+use crate::tools::{Tool, Tools};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// Metadata for `--output-template`; any of it may be missing.
+#[derive(Debug, Clone, Default)]
+pub struct MediaInfo {
+    pub title: Option<String>,
+    pub id: Option<String>,
+    pub uploader: Option<String>,
+    /// `YYYY-MM-DD`
+    pub upload_date: Option<String>,
+}
+
+/// Metadata from the `.info.json` yt-dlp writes with `--write-info-json`.
+pub fn read_info_json(path: &Path) -> MediaInfo {
+    let Some(info) = fs::read(path)
+        .ok()
+        .and_then(|b| serde_json::from_slice::<Value>(&b).ok())
+    else {
+        return MediaInfo::default();
+    };
+    let field = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|n| info.get(*n)?.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    MediaInfo {
+        title: field(&["title"]),
+        id: field(&["id"]),
+        uploader: field(&["uploader", "channel", "uploader_id"]),
+        upload_date: field(&["upload_date", "release_date"]).and_then(|d| normalize_date(&d)),
+    }
+}
+
+/// Metadata from a local file's container tags, via ffprobe.
+pub fn probe_local_tags(tools: &Tools, path: &Path) -> MediaInfo {
+    let Some(out) = tools.command(Tool::Ffprobe).ok().and_then(|mut c| {
+        c.args(["-v", "error", "-show_entries", "format_tags"])
+            .args(["-of", "json"])
+            .arg(path);
+        logging::output(&mut c).ok()
+    }) else {
+        return MediaInfo::default();
+    };
+    let json: Value = serde_json::from_slice(&out.stdout).unwrap_or_default();
+    // tag names are upper case in Matroska, lower case in MP4
+    let tags: Vec<(String, &str)> = json["format"]["tags"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(k, v)| Some((k.to_lowercase(), v.as_str()?.trim())))
+        .filter(|(_, v)| !v.is_empty())
+        .collect();
+    let field = |names: &[&str]| {
+        names.iter().find_map(|n| {
+            tags.iter()
+                .find(|(k, _)| k == n)
+                .map(|(_, v)| v.to_string())
+        })
+    };
+    MediaInfo {
+        title: field(&["title"]),
+        id: None,
+        uploader: field(&["artist", "album_artist", "author"]),
+        upload_date: field(&["date", "creation_time"]).and_then(|d| normalize_date(&d)),
+    }
+}
+
+/// `20240131`, `2024-01-31` or `2024-01-31T10:00:00Z` → `2024-01-31`.
+fn normalize_date(date: &str) -> Option<String> {
+    let digits: String = date
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '-')
+        .filter(char::is_ascii_digit)
+        .collect();
+    match digits.len() {
+        8 => Some(format!(
+            "{}-{}-{}",
+            &digits[..4],
+            &digits[4..6],
+            &digits[6..]
+        )),
+        4 => Some(digits),
+        _ => None,
+    }
+}
+
+/// Duration of a local media file in seconds, via ffprobe. `None` when unknown.
+pub fn probe_local_duration(tools: &Tools, path: &Path) -> Option<f64> {
+    let mut cmd = tools.command(Tool::Ffprobe).ok()?;
+    cmd.args(["-v", "error", "-show_entries", "format=duration"])
+        .args(["-of", "default=noprint_wrappers=1:nokey=1"])
+        .arg(path);
+    let out = logging::output(&mut cmd).ok()?;
+    parse_seconds(out.status.success(), &out.stdout)
+}
+
+/// Duration of a remote video in seconds, from yt-dlp metadata (no download).
+pub fn probe_remote_duration(tools: &Tools, url: &str) -> Option<f64> {
+    let mut cmd = tools.command(Tool::YtDlp).ok()?;
+    cmd.args(["--skip-download", "--no-playlist", "--no-warnings"])
+        .args(["--print", "duration"])
+        .arg(url);
+    let out = logging::output(&mut cmd).ok()?;
+    parse_seconds(out.status.success(), &out.stdout)
+}
+
+/// What vid2txt needs from a WAV file's `fmt ` and `data` chunks.
+struct WavHeader {
+    /// Integer PCM (format tag 1)
+    pcm: bool,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    byte_rate: u32,
+    data_bytes: u64,
+}
+
+fn read_wav_header(path: &Path) -> Option<WavHeader> {
+    let mut header = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(4096)
+        .read_to_end(&mut header)
+        .ok()?;
+    if header.get(0..4)? != b"RIFF" || header.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let u16_at = |i: usize| -> Option<u16> {
+        Some(u16::from_le_bytes(header.get(i..i + 2)?.try_into().ok()?))
+    };
+    let u32_at = |i: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(header.get(i..i + 4)?.try_into().ok()?))
+    };
+    let mut fmt = None;
+    let mut at = 12;
+    while at + 8 <= header.len() {
+        let size = u32_at(at + 4)?;
+        match &header[at..at + 4] {
+            b"fmt " => {
+                let body = at + 8;
+                fmt = Some((
+                    u16_at(body)? == 1,
+                    u16_at(body + 2)?,
+                    u32_at(body + 4)?,
+                    u32_at(body + 8)?,
+                    u16_at(body + 14)?,
+                ));
+            }
+            b"data" => {
+                let (pcm, channels, sample_rate, byte_rate, bits_per_sample) = fmt?;
+                // streamed WAVs leave the size at 0 or u32::MAX; use the file length
+                let data_bytes = match size {
+                    0 | u32::MAX => std::fs::metadata(path)
+                        .ok()?
+                        .len()
+                        .saturating_sub(at as u64 + 8),
+                    n => u64::from(n),
+                };
+                return Some(WavHeader {
+                    pcm,
+                    channels,
+                    sample_rate,
+                    bits_per_sample,
+                    byte_rate,
+                    data_bytes,
+                });
+            }
+            _ => {}
+        }
+        at += 8 + size as usize + (size as usize & 1);
+    }
+    None
+}
+
+/// Duration of a PCM WAV file in seconds, from its `fmt ` and `data` chunks.
+pub fn wav_duration(path: &Path) -> Option<f64> {
+    let h = read_wav_header(path)?;
+    (h.byte_rate > 0).then(|| h.data_bytes as f64 / f64::from(h.byte_rate))
+}
+
+/// Whether `path` already is what ffmpeg would extract: 16 kHz 16-bit PCM with
+/// `channels` channels.
+pub fn is_whisper_wav(path: &Path, channels: u16) -> bool {
+    read_wav_header(path).is_some_and(|h| {
+        h.pcm && h.channels == channels && h.sample_rate == 16_000 && h.bits_per_sample == 16
+    })
+}
+
+fn parse_seconds(ok: bool, stdout: &[u8]) -> Option<f64> {
+    if !ok {
+        return None;
+    }
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .next()?
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|d| d.is_finite() && *d > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 16 kHz mono 16-bit WAV with `data_bytes` of silence, after an extra chunk.
+    fn wav(data_size: u32, data_bytes: usize) -> Vec<u8> {
+        let mut v = Vec::new();
+        v.extend(b"RIFF");
+        v.extend(0u32.to_le_bytes());
+        v.extend(b"WAVE");
+        v.extend(b"LIST");
+        v.extend(3u32.to_le_bytes());
+        v.extend(b"abc\0"); // odd size, padded
+        v.extend(b"fmt ");
+        v.extend(16u32.to_le_bytes());
+        v.extend(1u16.to_le_bytes()); // PCM
+        v.extend(1u16.to_le_bytes()); // mono
+        v.extend(16_000u32.to_le_bytes());
+        v.extend(32_000u32.to_le_bytes()); // byte rate
+        v.extend(2u16.to_le_bytes());
+        v.extend(16u16.to_le_bytes());
+        v.extend(b"data");
+        v.extend(data_size.to_le_bytes());
+        v.resize(v.len() + data_bytes, 0);
+        v
+    }
+
+    fn duration(bytes: &[u8]) -> Option<f64> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.wav");
+        fs::write(&path, bytes).unwrap();
+        wav_duration(&path)
+    }
+
+    #[test]
+    fn reads_wav_durations() {
+        assert_eq!(duration(&wav(64_000, 64_000)), Some(2.0));
+        // streamed: the size comes from the file length
+        assert_eq!(duration(&wav(u32::MAX, 16_000)), Some(0.5));
+        assert_eq!(duration(&wav(0, 8_000)), Some(0.25));
+    }
+
+    #[test]
+    fn recognizes_wavs_ready_for_whisper() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.wav");
+        fs::write(&path, wav(64_000, 64_000)).unwrap();
+        assert!(is_whisper_wav(&path, 1));
+        assert!(!is_whisper_wav(&path, 2));
+
+        let mut stereo_44k = wav(64_000, 64_000);
+        stereo_44k[34..36].copy_from_slice(&2u16.to_le_bytes());
+        stereo_44k[36..40].copy_from_slice(&44_100u32.to_le_bytes());
+        fs::write(&path, stereo_44k).unwrap();
+        assert!(!is_whisper_wav(&path, 1));
+        assert!(!is_whisper_wav(&dir.path().join("missing.wav"), 1));
+    }
+
+    #[test]
+    fn rejects_non_wavs() {
+        assert_eq!(duration(b"ID3\x04 not a wav at all"), None);
+        let mut no_fmt = wav(10, 10);
+        no_fmt[24..28].copy_from_slice(b"junk");
+        assert_eq!(duration(&no_fmt), None);
+        assert_eq!(duration(&wav(10, 10)[..40]), None);
+    }
+
+    #[test]
+    fn parses_probe_output() {
+        assert_eq!(parse_seconds(true, b"12.5\n"), Some(12.5));
+        assert_eq!(parse_seconds(true, b"N/A\n"), None);
+        assert_eq!(parse_seconds(true, b"0\n"), None);
+        assert_eq!(parse_seconds(false, b"12.5\n"), None);
+    }
+}
@@ -0,0 +1,96 @@
+//! A canned-response HTTP server on localhost for tests of the HTTP clients.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A request as the server saw it.
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// Path including the query string
+    pub path: String,
+    /// Header names are lowercased
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    /// Serve `respond(&request) -> (status, content type, body)` until the test ends.
+    pub fn start(
+        respond: impl Fn(&Request) -> (u16, &'static str, String) + Send + 'static,
+    ) -> Self {
+        Self::start_with(move |req| {
+            let (status, content_type, body) = respond(req);
+            (
+                status,
+                vec![("Content-Type", content_type.to_string())],
+                body,
+            )
+        })
+    }
+
+    /// Like [`MockServer::start`], with any response headers.
+    pub fn start_with(
+        respond: impl Fn(&Request) -> (u16, Vec<(&'static str, String)>, String) + Send + 'static,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line.split_whitespace().nth(1).unwrap_or("").to_string();
+                let mut headers = Vec::new();
+                loop {
+                    line.clear();
+                    if reader.read_line(&mut line).unwrap() == 0 || line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((n, v)) = line.split_once(':') {
+                        headers.push((n.trim().to_ascii_lowercase(), v.trim().to_string()));
+                    }
+                }
+                let req = Request { path, headers };
+                // drain the body so the client doesn't see a reset
+                if let Some(len) = req.header("content-length").and_then(|l| l.parse().ok()) {
+                    let mut body = vec![0; len];
+                    reader.read_exact(&mut body).unwrap();
+                }
+                let (status, headers, body) = respond(&req);
+                seen.lock().unwrap().push(req);
+                let headers: String = headers
+                    .iter()
+                    .map(|(name, value)| format!("{name}: {value}\r\n"))
+                    .collect();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status} X\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+            }
+        });
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
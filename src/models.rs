@@ -1,7 +1,11 @@
-use crate::constants::HF_RESOLVE_URL;
-use crate::hf::{HfFile, is_quantized_name};
-use anyhow::{Result, anyhow};
-use dialoguer::{Select, theme::ColorfulTheme};
+use crate::constants::FUZZY_PICKER_THRESHOLD;
+use crate::error::Vid2txtError;
+use crate::hf::{HfFile, HfTokens, http_get, is_quantized_name};
+use crate::interrupt::{self, Partial};
+use crate::naming::with_ext;
+use crate::report::say;
+use anyhow::Result;
+use dialoguer::{FuzzySelect, Select, theme::ColorfulTheme};
 use indicatif::{ProgressBar, ProgressStyle};
 use sanitize_filename::sanitize;
 use std::fs::{self, File};
@@ -12,27 +16,40 @@ pub fn pick_model_interactive(
     files: &[HfFile],
     prefer_quantized: bool,
     models_dir: &Path,
-) -> Result<String> {
+    recommended: Option<usize>,
+) -> Result<HfFile> {
     if files.is_empty() {
-        return Err(anyhow!("No models found in Hugging Face API response"));
+        return Err(Vid2txtError::ModelResolution(
+            "No models found in Hugging Face API response".into(),
+        )
+        .into());
     }
 
     let theme = ColorfulTheme::default();
     let items: Vec<String> = files
         .iter()
-        .map(|f| {
+        .enumerate()
+        .map(|(i, f)| {
             let full = if is_quantized_name(&f.rfilename.to_lowercase()) {
                 "quant"
             } else {
                 "full-precision"
             };
             let size = f.size.map(format_size).unwrap_or_else(|| "?".into());
-            let local_flag = if models_dir.join(&f.rfilename).exists() {
+            let local_flag = if f.local_path(models_dir).exists() {
                 " (local)"
             } else {
                 ""
             };
-            format!("{}  [{} | {}]{}", f.rfilename, full, size, local_flag)
+            let rec_flag = if recommended == Some(i) {
+                " ★ recommended"
+            } else {
+                ""
+            };
+            format!(
+                "{}  [{} | {} | {}]{}{}",
+                f.rfilename, full, size, f.source.id, local_flag, rec_flag
+            )
         })
         .collect();
 
@@ -42,73 +59,135 @@ pub fn pick_model_interactive(
         "Pick a Whisper model (full-precision preferred)"
     };
 
-    let sel = Select::with_theme(&theme)
-        .with_prompt(prompt)
-        .default(0)
-        .items(&items)
-        .interact()?;
+    // long lists (several repos) are easier to navigate by typing; Esc cancels
+    let sel = if items.len() > FUZZY_PICKER_THRESHOLD {
+        FuzzySelect::with_theme(&theme)
+            .with_prompt(format!("{prompt} — type to filter"))
+            .default(recommended.unwrap_or(0))
+            .items(&items)
+            .interact_opt()?
+    } else {
+        Select::with_theme(&theme)
+            .with_prompt(prompt)
+            .default(recommended.unwrap_or(0))
+            .items(&items)
+            .interact_opt()?
+    };
 
-    Ok(files[sel].rfilename.clone())
+    let sel = sel.ok_or(Vid2txtError::Cancelled)?;
+    Ok(files[sel].clone())
 }
 
-pub fn resolve_or_download_model(
-    user_input: &str,
-    models_dir: &Path,
-    files: &[HfFile],
-    prefer_quantized: bool,
-    verbose: bool,
-) -> Result<PathBuf> {
+/// Resolve `user_input` without touching the catalog: an existing path, or a file
+/// already present in `models_dir`.
+pub fn resolve_local_model(user_input: &str, models_dir: &Path) -> Option<PathBuf> {
     // Existing path?
     let path = PathBuf::from(user_input);
     if path.exists() {
-        return Ok(path);
+        return Some(path);
     }
 
     // Model file in models dir?
     let candidate = models_dir.join(user_input);
     if candidate.exists() {
-        return Ok(candidate);
+        return Some(candidate);
+    }
+    None
+}
+
+pub fn resolve_or_download_model(
+    user_input: &str,
+    models_dir: &Path,
+    files: &[HfFile],
+    prefer_quantized: bool,
+    tokens: &HfTokens,
+    offline: bool,
+    verbose: bool,
+) -> Result<PathBuf> {
+    if let Some(p) = resolve_local_model(user_input, models_dir) {
+        return Ok(p);
     }
+    let file = match_model(user_input, models_dir, files, prefer_quantized, offline)?;
+    let token = tokens.for_repo(&file.source);
+    download_model_if_missing(file, models_dir, token, offline, verbose)
+}
 
-    // Treat as alias like "large-v3" and find best match
+/// The catalog entry `user_input` names; offline, only downloaded models qualify.
+pub fn match_model<'a>(
+    user_input: &str,
+    models_dir: &Path,
+    files: &'a [HfFile],
+    prefer_quantized: bool,
+    offline: bool,
+) -> Result<&'a HfFile> {
+    // Treat as alias like "large-v3" (or "owner/repo/large-v3") and find best match
     let needle = user_input.to_lowercase();
 
     // score: (0 better) exact contains + preference, else fallback
     let best = files
         .iter()
-        .filter(|f| f.rfilename.to_lowercase().contains(&needle))
+        .filter(|f| {
+            f.rfilename.to_lowercase().contains(&needle)
+                || format!("{}/{}", f.source.id, f.rfilename)
+                    .to_lowercase()
+                    .contains(&needle)
+        })
+        // offline: only models we already have are usable
+        .filter(|f| !offline || f.local_path(models_dir).exists())
         .min_by_key(|f| {
             let name = f.rfilename.to_lowercase();
             let is_q = is_quantized_name(&name);
             let pref_penalty = if prefer_quantized ^ is_q { 1u8 } else { 0u8 };
-            (pref_penalty, name)
-        })
-        .map(|f| f.rfilename.clone());
+            // upstream repo wins ties over fine-tunes/mirrors
+            (pref_penalty, !f.source.is_default(), name)
+        });
 
-    let filename = best.ok_or_else(|| {
-        anyhow!(
-            "Could not find a model matching '{}' in HF repo",
-            user_input
-        )
+    let file = best.ok_or_else(|| {
+        Vid2txtError::ModelResolution(if offline {
+            format!(
+                "No local model matching '{}' in {} (--offline forbids downloading)",
+                user_input,
+                models_dir.display()
+            )
+        } else {
+            format!(
+                "Could not find a model matching '{}' in the configured model repos",
+                user_input
+            )
+        })
     })?;
-
-    download_model_if_missing(&filename, models_dir, verbose)
+    Ok(file)
 }
 
 pub fn download_model_if_missing(
-    filename: &str,
+    file: &HfFile,
     models_dir: &Path,
+    token: Option<&str>,
+    offline: bool,
     verbose: bool,
 ) -> Result<PathBuf> {
-    let dest = models_dir.join(filename);
+    let dest = file.local_path(models_dir);
     if dest.exists() {
         return Ok(dest);
     }
-    fs::create_dir_all(models_dir)?;
-    let url = format!("{}{}?download=true", HF_RESOLVE_URL, filename);
+    if offline {
+        return Err(Vid2txtError::Network(format!(
+            "Model {} is not downloaded and --offline forbids network access",
+            file.rfilename
+        ))
+        .into());
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let url = file.source.resolve_url(&file.rfilename);
 
-    println!("⬇️  Downloading model: {}", filename);
-    let resp = reqwest::blocking::get(&url)?.error_for_status()?;
+    say!(
+        "⬇️  Downloading model: {} ({})",
+        file.rfilename,
+        file.source.id
+    );
+    let resp = http_get(&url, token)?;
     let total = resp.content_length();
 
     let pb = ProgressBar::new(total.unwrap_or(0));
@@ -118,11 +197,15 @@ pub fn download_model_if_missing(
     );
     pb.set_message("Downloading");
 
+    // a model cut short must not look downloaded next time
+    let part = with_ext(&dest, "part");
+    let partial = Partial::new(&part);
     let mut src = resp;
-    let mut file = File::create(&dest)?;
+    let mut file = File::create(&part)?;
     let mut buf = [0u8; 64 * 1024];
     let mut downloaded: u64 = 0;
     loop {
+        interrupt::check()?;
         let n = src.read(&mut buf)?;
         if n == 0 {
             break;
@@ -135,13 +218,33 @@ pub fn download_model_if_missing(
         pb.set_position(downloaded);
     }
     pb.finish_and_clear();
+    drop(file);
+    fs::rename(&part, &dest)?;
+    partial.done();
 
     if verbose {
-        println!("Saved model to {}", dest.display());
+        say!("Saved model to {}", dest.display());
     }
     Ok(dest)
 }
 
+/// The English-only sibling of a multilingual model file, if it is already downloaded:
+/// `ggml-base.bin` → `ggml-base.en.bin`, `ggml-small-q5_1.bin` → `ggml-small.en-q5_1.bin`.
+/// large/turbo models have no `.en` variant.
+pub fn english_variant(model: &Path) -> Option<PathBuf> {
+    let name = model.file_name()?.to_str()?;
+    let rest = name.strip_prefix("ggml-")?;
+    let size = ["tiny", "base", "small", "medium"]
+        .into_iter()
+        .find(|s| rest.starts_with(s))?;
+    let suffix = &rest[size.len()..];
+    if suffix.starts_with(".en") {
+        return None;
+    }
+    let candidate = model.with_file_name(format!("ggml-{size}.en{suffix}"));
+    candidate.exists().then_some(candidate)
+}
+
 pub fn format_size(bytes: u64) -> String {
     const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
     let mut size = bytes as f64;
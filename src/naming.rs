@@ -0,0 +1,244 @@
+//! Output names from `--output-template`: `{placeholder}`s are filled from the
+//! media's metadata and the run, and every path component is sanitized the same
+//! way, whether it came from the template or a value.
+
+use crate::clock;
+use crate::error::Vid2txtError;
+use crate::media::MediaInfo;
+use anyhow::Result;
+use sanitize_filename::sanitize;
+use std::ffi::OsString;
+use std::path::{Component, Path, PathBuf};
+
+/// Placeholders that need [`MediaInfo`] (an ffprobe run for local files).
+const METADATA_FIELDS: &[&str] = &["title", "id", "uploader", "upload_date"];
+const RUN_FIELDS: &[&str] = &["stem", "model", "lang", "date"];
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(String),
+    Field(&'static str),
+}
+
+/// A parsed template; one `Vec<Piece>` per `/`-separated path component.
+#[derive(Debug, Clone)]
+pub struct OutputTemplate {
+    components: Vec<Vec<Piece>>,
+}
+
+/// Everything a placeholder can refer to.
+pub struct NameFields<'a> {
+    pub info: &'a MediaInfo,
+    /// Sanitized input file stem, or the downloaded file's name for URLs
+    pub stem: &'a str,
+    /// Model (or backend) name
+    pub model: &'a str,
+    /// Language code, if known yet
+    pub lang: Option<&'a str>,
+}
+
+impl OutputTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let invalid = |why: String| -> anyhow::Error {
+            Vid2txtError::InvalidInput(format!("--output-template '{template}': {why}")).into()
+        };
+        let path = Path::new(template);
+        if path.has_root()
+            || path
+                .components()
+                .any(|c| matches!(c, Component::ParentDir | Component::Prefix(_)))
+        {
+            return Err(invalid("must be a relative path inside --out".into()));
+        }
+
+        let mut components = Vec::new();
+        for part in template.split(['/', '\\']) {
+            if part.is_empty() || part == "." {
+                continue;
+            }
+            let mut pieces = Vec::new();
+            let mut rest = part;
+            while let Some(start) = rest.find('{') {
+                if start > 0 {
+                    pieces.push(Piece::Text(rest[..start].to_string()));
+                }
+                let end = rest[start..]
+                    .find('}')
+                    .ok_or_else(|| invalid("unclosed '{'".into()))?;
+                let name = &rest[start + 1..start + end];
+                let field = METADATA_FIELDS
+                    .iter()
+                    .chain(RUN_FIELDS)
+                    .find(|f| **f == name)
+                    .ok_or_else(|| {
+                        invalid(format!(
+                            "unknown placeholder {{{name}}} (known: {})",
+                            METADATA_FIELDS
+                                .iter()
+                                .chain(RUN_FIELDS)
+                                .map(|f| format!("{{{f}}}"))
+                                .collect::<Vec<_>>()
+                                .join(" ")
+                        ))
+                    })?;
+                pieces.push(Piece::Field(field));
+                rest = &rest[start + end + 1..];
+            }
+            if !rest.is_empty() {
+                pieces.push(Piece::Text(rest.to_string()));
+            }
+            components.push(pieces);
+        }
+        if components.is_empty() {
+            return Err(invalid("names no file".into()));
+        }
+        Ok(Self { components })
+    }
+
+    /// Whether rendering needs the media's metadata.
+    pub fn uses_metadata(&self) -> bool {
+        self.fields().any(|f| METADATA_FIELDS.contains(&f))
+    }
+
+    /// Whether the name can change once the model and language are final.
+    pub fn uses_outcome(&self) -> bool {
+        self.fields().any(|f| f == "model" || f == "lang")
+    }
+
+    fn fields(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.components.iter().flatten().filter_map(|p| match p {
+            Piece::Field(f) => Some(*f),
+            Piece::Text(_) => None,
+        })
+    }
+
+    /// The output path relative to `--out`, without extension.
+    pub fn render(&self, fields: &NameFields) -> PathBuf {
+        self.components
+            .iter()
+            .map(|pieces| {
+                let text: String = pieces
+                    .iter()
+                    .map(|p| match p {
+                        Piece::Text(t) => t.clone(),
+                        Piece::Field(f) => sanitize(value(f, fields)),
+                    })
+                    .collect();
+                let name = sanitize(text.trim());
+                if name.is_empty() { "_".into() } else { name }
+            })
+            .collect()
+    }
+}
+
+fn value(field: &str, f: &NameFields) -> String {
+    let unknown = || "unknown".to_string();
+    match field {
+        "title" => f.info.title.clone().unwrap_or_else(|| f.stem.to_string()),
+        "id" => f.info.id.clone().unwrap_or_else(|| f.stem.to_string()),
+        "uploader" => f.info.uploader.clone().unwrap_or_else(unknown),
+        "upload_date" => f.info.upload_date.clone().unwrap_or_else(unknown),
+        "stem" => f.stem.to_string(),
+        "model" => f.model.to_string(),
+        "lang" => f.lang.map(str::to_string).unwrap_or_else(unknown),
+        "date" => clock::today(),
+        _ => unreachable!("placeholders are checked by OutputTemplate::parse"),
+    }
+}
+
+/// Metadata rendered as its own `{placeholder}`, for names previewed before the
+/// metadata is known (`--dry-run` on a URL).
+pub fn unresolved_info() -> MediaInfo {
+    MediaInfo {
+        title: Some("{title}".into()),
+        id: Some("{id}".into()),
+        uploader: Some("{uploader}".into()),
+        upload_date: Some("{upload_date}".into()),
+    }
+}
+
+/// `base` plus `.ext`, keeping any dots already in the name (`talk v1.2` → `talk v1.2.txt`).
+pub fn with_ext(base: &Path, ext: &str) -> PathBuf {
+    let mut s = OsString::from(base.as_os_str());
+    s.push(".");
+    s.push(ext);
+    s.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, info: &MediaInfo, lang: Option<&str>) -> PathBuf {
+        OutputTemplate::parse(template)
+            .unwrap()
+            .render(&NameFields {
+                info,
+                stem: "talk",
+                model: "ggml-base",
+                lang,
+            })
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let info = MediaInfo {
+            title: Some("A/B: the talk?".into()),
+            uploader: Some("Someone".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            render("{uploader}/{title} [{model}].{lang}", &info, Some("de")),
+            Path::new("Someone").join("AB the talk [ggml-base].de")
+        );
+        // missing metadata falls back to the stem, or "unknown"
+        assert_eq!(
+            render("{id}-{upload_date}-{lang}", &MediaInfo::default(), None),
+            Path::new("talk-unknown-unknown")
+        );
+        assert_eq!(
+            render("./x//{stem}", &info, None),
+            Path::new("x").join("talk")
+        );
+    }
+
+    #[test]
+    fn never_renders_an_empty_component() {
+        let info = MediaInfo {
+            title: Some("  ".into()),
+            ..Default::default()
+        };
+        assert_eq!(render("{title}", &info, None), Path::new("_"));
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        for t in [
+            "/abs/{stem}",
+            "../{stem}",
+            "a/../b",
+            "{nope}",
+            "{stem",
+            "",
+            "./",
+        ] {
+            assert!(OutputTemplate::parse(t).is_err(), "{t}");
+        }
+    }
+
+    #[test]
+    fn knows_what_it_needs() {
+        let t = OutputTemplate::parse("{stem}").unwrap();
+        assert!(!t.uses_metadata() && !t.uses_outcome());
+        let t = OutputTemplate::parse("{upload_date}/{stem}.{lang}").unwrap();
+        assert!(t.uses_metadata() && t.uses_outcome());
+    }
+
+    #[test]
+    fn appends_extensions() {
+        assert_eq!(
+            with_ext(Path::new("out/talk v1.2"), "txt"),
+            Path::new("out/talk v1.2.txt")
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use crate::constants::STDERR_TAIL_LINES;
+use crate::interrupt;
+use crate::logging::{self, Level};
+use crate::report;
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde_json::json;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Resolution of the determinate bar (per mille, so short clips still move).
+const BAR_LEN: u64 = 1000;
+
+/// One pipeline stage (download, audio extraction, transcription) on the terminal.
+///
+/// Starts as a spinner and turns into a bar with an ETA once the tool reports how
+/// far it got. Cheap to clone; clones drive the same bar. With `--json-events` the
+/// stage also reports `stage_started`, `progress` and `stage_finished` events.
+#[derive(Clone)]
+pub struct Progress {
+    bar: ProgressBar,
+    /// Stage id in events, e.g. "download"
+    name: &'static str,
+}
+
+impl Progress {
+    pub fn stage(name: &'static str, msg: impl Into<String>) -> Self {
+        let msg = msg.into();
+        report::event("stage_started", json!({ "stage": name, "message": msg }));
+        logging::record("STAGE", &format!("{name}: {msg}"));
+        // -q: no bars at all
+        let bar = if logging::level() == Level::Quiet {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new_spinner()
+        };
+        bar.set_style(
+            ProgressStyle::with_template("{spinner} {msg}")
+                .unwrap()
+                .tick_chars("⠇⠋⠙⠸⠴⠦⠇"),
+        );
+        bar.set_message(msg);
+        bar.enable_steady_tick(Duration::from_millis(100));
+        Self { bar, name }
+    }
+
+    pub fn set_message(&self, msg: impl Into<String>) {
+        self.bar.set_message(msg.into());
+    }
+
+    /// Report completion as a fraction in 0.0..=1.0.
+    pub fn set_fraction(&self, fraction: f64) {
+        if self.bar.length().is_none() {
+            self.bar.set_style(
+                ProgressStyle::with_template(
+                    "{spinner} {msg} [{bar:30}] {percent:>3}% (ETA {eta})",
+                )
+                .unwrap()
+                .tick_chars("⠇⠋⠙⠸⠴⠦⠇")
+                .progress_chars("=> "),
+            );
+            self.bar.set_length(BAR_LEN);
+            self.bar.reset_eta();
+        }
+        let before = self.bar.position() * 100 / BAR_LEN;
+        self.bar
+            .set_position((fraction.clamp(0.0, 1.0) * BAR_LEN as f64) as u64);
+        let percent = self.bar.position() * 100 / BAR_LEN;
+        if percent != before {
+            report::event(
+                "progress",
+                json!({ "stage": self.name, "percent": percent }),
+            );
+        }
+    }
+
+    pub fn set_percent(&self, percent: u8) {
+        self.set_fraction(f64::from(percent) / 100.0);
+    }
+
+    /// Completion in percent, or `None` while still a spinner.
+    #[cfg(test)]
+    pub fn percent(&self) -> Option<u64> {
+        self.bar.length().map(|len| self.bar.position() * 100 / len)
+    }
+
+    /// Print a line above the bar (for `--verbose` passthrough).
+    pub fn println(&self, line: impl AsRef<str>) {
+        // indicatif drops println on hidden bars (no terminal)
+        let line = format!("{}{}", logging::console_prefix(), line.as_ref());
+        if self.bar.is_hidden() {
+            eprintln!("{line}");
+        } else {
+            self.bar.println(line);
+        }
+    }
+
+    pub fn finish(&self) {
+        if !self.bar.is_finished() {
+            report::event(
+                "stage_finished",
+                json!({ "stage": self.name, "seconds": self.bar.elapsed().as_secs_f64() }),
+            );
+        }
+        self.bar.finish_and_clear();
+    }
+}
+
+/// How a tool run by [`run_with_progress`] ended.
+pub struct ToolRun {
+    pub status: ExitStatus,
+    /// The last [`STDERR_TAIL_LINES`] stderr lines that weren't progress, for errors
+    pub stderr_tail: Vec<String>,
+}
+
+/// Run `cmd`, feeding every stdout/stderr line to `on_line`, which returns true when
+/// it consumed the line as progress. With `verbose` the remaining lines are echoed;
+/// either way the last stderr lines are kept so a failure can report them.
+pub fn run_with_progress(
+    cmd: &mut Command,
+    verbose: bool,
+    progress: &Progress,
+    mut on_line: impl FnMut(&str) -> bool,
+) -> Result<ToolRun> {
+    let tool = logging::tool_name(cmd);
+    logging::command(cmd);
+    let (mut child, _group) =
+        interrupt::spawn(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()))
+            .with_context(|| format!("Failed to start {}", cmd.get_program().to_string_lossy()))?;
+
+    let (tx, rx) = mpsc::channel::<(bool, String)>();
+    let readers = [
+        spawn_reader(
+            child.stdout.take().expect("piped stdout"),
+            false,
+            tx.clone(),
+        ),
+        spawn_reader(child.stderr.take().expect("piped stderr"), true, tx),
+    ];
+
+    let mut stderr_tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+    for (is_stderr, line) in rx {
+        logging::child_line(&tool, is_stderr, &line);
+        if on_line(&line) {
+            continue;
+        }
+        if verbose {
+            progress.println(&line);
+        }
+        if is_stderr {
+            if stderr_tail.len() == STDERR_TAIL_LINES {
+                stderr_tail.pop_front();
+            }
+            stderr_tail.push_back(line);
+        }
+    }
+    for r in readers {
+        let _ = r.join();
+    }
+
+    let status = child.wait()?;
+    logging::child_exit(&tool, &status);
+    Ok(ToolRun {
+        status,
+        stderr_tail: stderr_tail.into(),
+    })
+}
+
+/// Forward lines from `pipe`; `\r` also ends a line since tools redraw in place.
+fn spawn_reader(
+    pipe: impl Read + Send + 'static,
+    is_stderr: bool,
+    tx: mpsc::Sender<(bool, String)>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    for part in String::from_utf8_lossy(&buf).split(['\r', '\n']) {
+                        if !part.trim().is_empty()
+                            && tx.send((is_stderr, part.to_string())).is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,449 @@
+use crate::hf::{HfFile, is_quantized_name};
+use crate::models::format_size;
+use regex::Regex;
+use sysinfo::System;
+
+const MIB: u64 = 1024 * 1024;
+
+/// When the media duration is unknown, plan for a clip of this length (seconds).
+const ASSUMED_DURATION_SECS: f64 = 600.0;
+/// Short clips may take at least this long (seconds), so they get a better model.
+const MIN_TIME_BUDGET_SECS: f64 = 300.0;
+
+/// What the host can offer to whisper.cpp.
+#[derive(Debug, Clone)]
+pub struct Hardware {
+    pub total_memory: u64,
+    pub available_memory: u64,
+    pub cores: usize,
+    pub simd: Vec<&'static str>,
+}
+
+impl Hardware {
+    pub fn detect() -> Self {
+        let mut sys = System::new();
+        sys.refresh_memory();
+        Self {
+            total_memory: sys.total_memory(),
+            available_memory: sys.available_memory(),
+            cores: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            simd: detect_simd(),
+        }
+    }
+
+    /// How much slower than the reference machine (8 cores with AVX2/NEON) we expect to be.
+    fn slowdown(&self) -> f64 {
+        let simd_penalty = if self.simd.iter().any(|s| matches!(*s, "AVX2" | "NEON")) {
+            1.0
+        } else {
+            3.0
+        };
+        8.0 / self.cores.max(1) as f64 * simd_penalty
+    }
+
+    pub fn summary(&self) -> String {
+        let simd = if self.simd.is_empty() {
+            "no SIMD".to_string()
+        } else {
+            self.simd.join("/")
+        };
+        format!(
+            "{} cores, {simd}, {} free of {} RAM",
+            self.cores,
+            format_size(self.available_memory),
+            format_size(self.total_memory)
+        )
+    }
+}
+
+fn detect_simd() -> Vec<&'static str> {
+    #[allow(unused_mut)]
+    let mut v = Vec::new();
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::arch::is_x86_feature_detected!("avx512f") {
+            v.push("AVX512");
+        }
+        if std::arch::is_x86_feature_detected!("avx2") {
+            v.push("AVX2");
+        }
+        if std::arch::is_x86_feature_detected!("fma") {
+            v.push("FMA");
+        }
+        if std::arch::is_x86_feature_detected!("f16c") {
+            v.push("F16C");
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            v.push("NEON");
+        }
+    }
+    v
+}
+
+/// Rough characteristics of a model family, derived from its file name.
+struct ModelProfile {
+    /// tiny=1 … large=5
+    rank: u8,
+    /// Real-time factor on the reference machine (processing time / audio time)
+    rtf: f64,
+    /// Size of the f16 file, used when the catalog has no size
+    f16_bytes: u64,
+    english_only: bool,
+    /// turbo/distil variants trade some accuracy for speed
+    fast_variant: bool,
+}
+
+fn profile(rfilename: &str) -> Option<ModelProfile> {
+    let name = rfilename.to_lowercase();
+    let re = Regex::new(r"(tiny|base|small|medium|large)").unwrap();
+    let class = re.captures(&name)?.get(1)?.as_str();
+    let (rank, rtf, f16_mib) = match class {
+        "tiny" => (1, 0.03, 75),
+        "base" => (2, 0.06, 142),
+        "small" => (3, 0.18, 466),
+        "medium" => (4, 0.5, 1533),
+        _ => (5, 1.0, 2950),
+    };
+    let fast_variant = name.contains("turbo") || name.contains("distil");
+    let (rtf, f16_mib) = if fast_variant {
+        (rtf * 0.35, f16_mib / 2)
+    } else {
+        (rtf, f16_mib)
+    };
+    Some(ModelProfile {
+        rank,
+        rtf,
+        f16_bytes: f16_mib * MIB,
+        english_only: name.contains(".en"),
+        fast_variant,
+    })
+}
+
+/// Download size of a catalog entry, estimated from its name when the API had none.
+fn model_bytes(f: &HfFile) -> Option<u64> {
+    if let Some(s) = f.size {
+        return Some(s);
+    }
+    let p = profile(&f.rfilename)?;
+    let name = f.rfilename.to_lowercase();
+    let factor = if name.contains("q4") {
+        0.3
+    } else if name.contains("q5") {
+        0.37
+    } else if name.contains("q8") {
+        0.55
+    } else {
+        1.0
+    };
+    Some((p.f16_bytes as f64 * factor) as u64)
+}
+
+/// Approximate peak RAM whisper.cpp needs for a model file of `file_bytes`
+/// (weights plus compute buffers; fitted to the table in whisper.cpp's README).
+pub fn estimated_memory(file_bytes: u64) -> u64 {
+    file_bytes / 4 * 5 + 200 * MIB
+}
+
+pub fn is_english(language: &str) -> bool {
+    matches!(language.to_lowercase().as_str(), "en" | "english")
+}
+
+pub struct Recommendation {
+    /// Index into the catalog slice passed to [`recommend`]
+    pub index: usize,
+    pub reason: String,
+}
+
+/// Pick the best model the host can run in reasonable time: it must fit in 80% of free
+/// RAM and finish in about real time (or [`MIN_TIME_BUDGET_SECS`] for short clips).
+/// Among those, bigger models win, then `.en` variants for English, then the
+/// preferred precision, then full (non turbo/distil) variants.
+pub fn recommend(
+    files: &[HfFile],
+    hw: &Hardware,
+    duration_secs: Option<f64>,
+    english: bool,
+    prefer_quantized: bool,
+) -> Option<Recommendation> {
+    let duration = duration_secs.unwrap_or(ASSUMED_DURATION_SECS);
+    let budget = duration.max(MIN_TIME_BUDGET_SECS);
+    let mem_limit = hw.available_memory / 10 * 8;
+
+    let candidates: Vec<(usize, ModelProfile, u64, f64)> = files
+        .iter()
+        .enumerate()
+        .filter_map(|(i, f)| {
+            let p = profile(&f.rfilename)?;
+            let mem = estimated_memory(model_bytes(f)?);
+            let secs = p.rtf * hw.slowdown() * duration;
+            Some((i, p, mem, secs))
+        })
+        .filter(|(_, p, _, _)| english || !p.english_only)
+        .collect();
+
+    let fits =
+        |(_, _, mem, secs): &&(usize, ModelProfile, u64, f64)| *mem <= mem_limit && *secs <= budget;
+    let key = |(i, p, _, _): &&(usize, ModelProfile, u64, f64)| {
+        let quant = is_quantized_name(&files[*i].rfilename.to_lowercase());
+        (
+            p.rank,
+            english && p.english_only,
+            quant == prefer_quantized,
+            !p.fast_variant,
+            files[*i].source.is_default(),
+        )
+    };
+
+    let (best, fallback) = match candidates.iter().filter(fits).max_by_key(key) {
+        Some(best) => (best, false),
+        // Nothing fits comfortably: take the lightest model we know of
+        None => (
+            candidates
+                .iter()
+                .min_by_key(|(_, p, mem, _)| (p.rank, *mem))?,
+            true,
+        ),
+    };
+
+    let (index, _, mem, secs) = best;
+    let media = match duration_secs {
+        Some(d) => format!("{} of audio", format_secs(d)),
+        None => format!("an assumed {} of audio", format_secs(duration)),
+    };
+    let reason = format!(
+        "{}needs ~{} RAM, est. {} for {media} ({})",
+        if fallback {
+            "nothing fits comfortably; lightest model, "
+        } else {
+            ""
+        },
+        format_size(*mem),
+        format_secs(*secs),
+        hw.summary()
+    );
+    Some(Recommendation {
+        index: *index,
+        reason,
+    })
+}
+
+fn format_secs(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    if secs < 60 {
+        format!("{secs}s")
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FallbackDirection {
+    /// The model didn't fit: a quantized build of the same size, else the next size down
+    Smaller,
+    /// The result was poor: the next size up
+    Larger,
+}
+
+/// Catalog entry to retry with after the model file `current` failed, skipping the
+/// file names in `tried`. Keeps `.en` vs multilingual like `current` where possible.
+pub fn fallback_model(
+    files: &[HfFile],
+    current: &str,
+    direction: FallbackDirection,
+    english: bool,
+    tried: &[String],
+) -> Option<usize> {
+    let cur = profile(current)?;
+    let cur_quant = is_quantized_name(&current.to_lowercase());
+
+    let candidates = files.iter().enumerate().filter_map(|(i, f)| {
+        if tried.contains(&f.rfilename) {
+            return None;
+        }
+        let p = profile(&f.rfilename)?;
+        if p.english_only && !english {
+            return None;
+        }
+        let quant = is_quantized_name(&f.rfilename.to_lowercase());
+        Some((i, p, quant, model_bytes(f).unwrap_or(u64::MAX)))
+    });
+
+    match direction {
+        FallbackDirection::Smaller => candidates
+            .filter(|(_, p, quant, _)| {
+                p.rank < cur.rank || (p.rank == cur.rank && *quant && !cur_quant)
+            })
+            .max_by_key(|(i, p, quant, bytes)| {
+                (
+                    p.rank,
+                    !quant,
+                    p.english_only == cur.english_only,
+                    files[*i].source.is_default(),
+                    std::cmp::Reverse(*bytes),
+                )
+            })
+            .map(|(i, ..)| i),
+        FallbackDirection::Larger => candidates
+            .filter(|(_, p, _, _)| p.rank > cur.rank)
+            .min_by_key(|(i, p, quant, _)| {
+                (
+                    p.rank,
+                    *quant != cur_quant,
+                    p.english_only != cur.english_only,
+                    p.fast_variant,
+                    !files[*i].source.is_default(),
+                )
+            })
+            .map(|(i, ..)| i),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hf::ModelRepo;
+
+    fn catalog(names: &[&str]) -> Vec<HfFile> {
+        names
+            .iter()
+            .map(|n| HfFile {
+                rfilename: n.to_string(),
+                size: None,
+                source: ModelRepo::default(),
+            })
+            .collect()
+    }
+
+    fn hw(available_mib: u64, cores: usize) -> Hardware {
+        Hardware {
+            total_memory: 65_536 * MIB,
+            available_memory: available_mib * MIB,
+            cores,
+            simd: vec!["AVX2"],
+        }
+    }
+
+    const ALL: &[&str] = &[
+        "ggml-tiny.bin",
+        "ggml-base.bin",
+        "ggml-base.en.bin",
+        "ggml-small.bin",
+        "ggml-small-q5_1.bin",
+        "ggml-medium.bin",
+        "ggml-large-v3.bin",
+        "ggml-large-v3-turbo.bin",
+    ];
+
+    fn pick(hw: &Hardware, secs: Option<f64>, english: bool, quantized: bool) -> &'static str {
+        let files = catalog(ALL);
+        let r = recommend(&files, hw, secs, english, quantized).unwrap();
+        ALL[r.index]
+    }
+
+    #[test]
+    fn recommends_the_largest_model_that_fits() {
+        let hour = Some(3600.0);
+        assert_eq!(
+            pick(&hw(32_768, 8), hour, false, false),
+            "ggml-large-v3.bin"
+        );
+        // turbo is only a tie-breaker: it wins when full large is too slow
+        assert_eq!(
+            pick(&hw(8192, 4), hour, false, false),
+            "ggml-large-v3-turbo.bin"
+        );
+        assert_eq!(pick(&hw(8192, 2), hour, false, false), "ggml-small.bin");
+        // 10 minutes assumed; small fits in 80% of 1 GiB, medium does not
+        assert_eq!(pick(&hw(1024, 8), None, false, false), "ggml-small.bin");
+        assert_eq!(pick(&hw(1024, 8), None, false, true), "ggml-small-q5_1.bin");
+    }
+
+    #[test]
+    fn prefers_english_models_for_english() {
+        let hw = hw(512, 1);
+        assert_eq!(pick(&hw, Some(60.0), true, false), "ggml-base.en.bin");
+        assert_eq!(pick(&hw, Some(60.0), false, false), "ggml-base.bin");
+    }
+
+    #[test]
+    fn falls_back_to_the_lightest_model() {
+        let files = catalog(ALL);
+        let r = recommend(&files, &hw(0, 1), Some(60.0), false, false).unwrap();
+        assert_eq!(ALL[r.index], "ggml-tiny.bin");
+        assert!(
+            r.reason.starts_with("nothing fits comfortably"),
+            "{}",
+            r.reason
+        );
+        assert!(
+            recommend(
+                &catalog(&["ggml-silero.bin"]),
+                &hw(8, 8),
+                None,
+                false,
+                false
+            )
+            .is_none()
+        );
+    }
+
+    fn fallback(
+        current: &str,
+        direction: FallbackDirection,
+        tried: &[&str],
+    ) -> Option<&'static str> {
+        let files = catalog(ALL);
+        let tried: Vec<String> = tried.iter().map(|t| t.to_string()).collect();
+        fallback_model(&files, current, direction, false, &tried).map(|i| ALL[i])
+    }
+
+    #[test]
+    fn falls_back_to_smaller_models() {
+        use FallbackDirection::Smaller;
+        // a quantized build of the same size first
+        assert_eq!(
+            fallback("ggml-small.bin", Smaller, &[]),
+            Some("ggml-small-q5_1.bin")
+        );
+        assert_eq!(
+            fallback("ggml-small.bin", Smaller, &["ggml-small-q5_1.bin"]),
+            Some("ggml-base.bin")
+        );
+        assert_eq!(
+            fallback("ggml-small-q5_1.bin", Smaller, &[]),
+            Some("ggml-base.bin")
+        );
+        assert_eq!(fallback("ggml-tiny.bin", Smaller, &[]), None);
+    }
+
+    #[test]
+    fn falls_back_to_larger_models() {
+        use FallbackDirection::Larger;
+        assert_eq!(
+            fallback("ggml-base.bin", Larger, &[]),
+            Some("ggml-small.bin")
+        );
+        assert_eq!(
+            fallback("ggml-small-q5_1.bin", Larger, &[]),
+            Some("ggml-medium.bin")
+        );
+        // full variants before turbo
+        assert_eq!(
+            fallback("ggml-medium.bin", Larger, &[]),
+            Some("ggml-large-v3.bin")
+        );
+        assert_eq!(
+            fallback("ggml-medium.bin", Larger, &["ggml-large-v3.bin"]),
+            Some("ggml-large-v3-turbo.bin")
+        );
+        assert_eq!(fallback("ggml-large-v3.bin", Larger, &[]), None);
+        assert_eq!(fallback("custom.bin", Larger, &[]), None);
+    }
+}
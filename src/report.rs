@@ -0,0 +1,328 @@
+//! Machine-readable output of `transcribe --json` (one result object per input) and
+//! `--json-events` (an NDJSON stream of progress events plus those results). In both
+//! modes stdout carries only JSON, so human-readable messages move to stderr.
+
+use crate::error;
+use crate::transcript::Attempt;
+use serde::Serialize;
+use serde_json::json;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Human,
+    /// One JSON object per processed input
+    Json,
+    /// `Json` plus stage/progress events, every line tagged with `"event"`
+    Events,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_mode(mode: Mode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn mode() -> Mode {
+    #[cfg(test)]
+    if let Some(mode) = capture::mode() {
+        return mode;
+    }
+    match MODE.load(Ordering::Relaxed) {
+        1 => Mode::Json,
+        2 => Mode::Events,
+        _ => Mode::Human,
+    }
+}
+
+/// `println!` for human-readable messages: stdout normally, stderr when stdout is
+/// reserved for JSON; silenced by `-q`, copied to the `--log-file`.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        $crate::logging::info(&format!($($arg)*))
+    };
+}
+pub(crate) use say;
+
+/// `eprintln!` for warnings and per-input failures, copied to the `--log-file`.
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::logging::warn(&format!($($arg)*))
+    };
+}
+pub(crate) use warning;
+
+/// Write one JSON line to stdout.
+fn emit_line(value: &impl Serialize) {
+    #[cfg(test)]
+    if capture::push(value) {
+        return;
+    }
+    let mut out = std::io::stdout().lock();
+    if let Ok(line) = serde_json::to_string(value) {
+        let _ = writeln!(out, "{line}");
+        let _ = out.flush();
+    }
+}
+
+#[derive(Serialize)]
+struct Tagged<'a, T: Serialize> {
+    event: &'a str,
+    #[serde(flatten)]
+    data: T,
+}
+
+/// A progress event; dropped unless in [`Mode::Events`].
+pub fn event(event: &str, data: impl Serialize) {
+    if mode() == Mode::Events {
+        emit_line(&Tagged { event, data });
+    }
+}
+
+pub fn input_started(input: &str) {
+    event("input_started", json!({ "input": input }));
+}
+
+/// The result for one input: a bare object with `--json`, an `input_finished`
+/// event with `--json-events`, nothing otherwise.
+pub fn input_finished(report: &InputReport) {
+    match mode() {
+        Mode::Human => {}
+        Mode::Json => emit_line(report),
+        Mode::Events => emit_line(&Tagged {
+            event: "input_finished",
+            data: report,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    #[default]
+    Ok,
+    Failed,
+}
+
+/// Wall-clock seconds per stage.
+#[derive(Debug, Default, Serialize)]
+pub struct Timings {
+    /// Download (yt-dlp) or extraction (ffmpeg) of the audio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acquire: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detect_language: Option<f64>,
+    /// All transcription attempts together
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcribe: Option<f64>,
+    pub total: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl ErrorReport {
+    pub fn new(e: &anyhow::Error) -> Self {
+        Self {
+            kind: error::classify(e).0,
+            message: format!("{e:#}"),
+        }
+    }
+}
+
+/// What happened to one input; filled in as the pipeline gets further, so a failed
+/// input still reports the paths it got to.
+#[derive(Debug, Default, Serialize)]
+pub struct InputReport {
+    pub input: String,
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<PathBuf>,
+    /// Word timings (`--word-timestamps`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript_json: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wav: Option<PathBuf>,
+    pub backend: String,
+    /// Model file actually used (after any fallback)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_probability: Option<f32>,
+    /// Length of the extracted audio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_seconds: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segments: Option<usize>,
+    /// Spans removed by the hallucination filter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filtered: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<Attempt>,
+    pub timings: Timings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorReport>,
+    /// This input's own log (`--log-file` in a batch)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log: Option<PathBuf>,
+}
+
+/// Reports of the current thread in a chosen mode, collected instead of printed.
+#[cfg(test)]
+pub mod capture {
+    use super::Mode;
+    use serde::Serialize;
+    use serde_json::Value;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static LINES: RefCell<Option<(Mode, Vec<Value>)>> = const { RefCell::new(None) };
+    }
+
+    /// Run `f` with reports in `mode` and return the JSON lines it wrote.
+    pub fn lines(mode: Mode, f: impl FnOnce()) -> Vec<Value> {
+        LINES.set(Some((mode, Vec::new())));
+        f();
+        LINES.take().map(|(_, lines)| lines).unwrap_or_default()
+    }
+
+    pub(super) fn mode() -> Option<Mode> {
+        LINES.with_borrow(|c| c.as_ref().map(|(mode, _)| *mode))
+    }
+
+    pub(super) fn push(value: &impl Serialize) -> bool {
+        LINES.with_borrow_mut(|c| match c {
+            Some((_, lines)) => {
+                lines.push(serde_json::to_value(value).unwrap());
+                true
+            }
+            None => false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Vid2txtError;
+    use crate::progress::Progress;
+    use serde_json::json;
+
+    fn finished() -> InputReport {
+        InputReport {
+            input: "talk.mp4".into(),
+            transcript: Some("out/talk.txt".into()),
+            backend: "whisper-cli".into(),
+            model: Some("ggml-base.bin".into()),
+            language: Some("en".into()),
+            segments: Some(12),
+            timings: Timings {
+                acquire: Some(1.5),
+                transcribe: Some(8.0),
+                total: 10.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn streams_tagged_events() {
+        let lines = capture::lines(Mode::Events, || {
+            input_started("talk.mp4");
+            let progress = Progress::stage("extract", "Extracting audio…");
+            progress.set_percent(42);
+            progress.set_percent(100);
+            progress.finish();
+            input_finished(&finished());
+        });
+        let events: Vec<&str> = lines.iter().map(|l| l["event"].as_str().unwrap()).collect();
+        assert_eq!(
+            events,
+            [
+                "input_started",
+                "stage_started",
+                "progress",
+                "progress",
+                "stage_finished",
+                "input_finished"
+            ]
+        );
+        assert_eq!(
+            lines[0],
+            json!({ "event": "input_started", "input": "talk.mp4" })
+        );
+        assert_eq!(
+            lines[1],
+            json!({ "event": "stage_started", "stage": "extract", "message": "Extracting audio…" })
+        );
+        assert_eq!(
+            lines[2],
+            json!({ "event": "progress", "stage": "extract", "percent": 42 })
+        );
+        assert_eq!(lines[4]["stage"], "extract");
+        assert!(lines[4]["seconds"].is_f64());
+        assert_eq!(lines[5]["input"], "talk.mp4");
+        assert_eq!(lines[5]["status"], "ok");
+    }
+
+    #[test]
+    fn json_mode_prints_only_results() {
+        let lines = capture::lines(Mode::Json, || {
+            input_started("talk.mp4");
+            Progress::stage("extract", "Extracting audio…").finish();
+            input_finished(&finished());
+        });
+        assert_eq!(
+            lines,
+            [json!({
+                "input": "talk.mp4",
+                "status": "ok",
+                "transcript": "out/talk.txt",
+                "backend": "whisper-cli",
+                "model": "ggml-base.bin",
+                "language": "en",
+                "segments": 12,
+                "timings": { "acquire": 1.5, "transcribe": 8.0, "total": 10.0 }
+            })]
+        );
+        assert!(capture::lines(Mode::Human, || input_finished(&finished())).is_empty());
+    }
+
+    #[test]
+    fn reports_failures() {
+        let e = anyhow::Error::from(Vid2txtError::Download {
+            message: "yt-dlp failed".into(),
+            stderr: vec!["ERROR: Video unavailable".into()],
+        })
+        .context("https://example.com/v");
+        let report = InputReport {
+            input: "https://example.com/v".into(),
+            status: Status::Failed,
+            backend: "whisper-cli".into(),
+            error: Some(ErrorReport::new(&e)),
+            ..Default::default()
+        };
+        let lines = capture::lines(Mode::Json, || input_finished(&report));
+        assert_eq!(
+            lines,
+            [json!({
+                "input": "https://example.com/v",
+                "status": "failed",
+                "backend": "whisper-cli",
+                "timings": { "total": 0.0 },
+                "error": {
+                    "kind": "download",
+                    "message": "https://example.com/v: yt-dlp failed; last output:\n    ERROR: Video unavailable"
+                }
+            })]
+        );
+    }
+}
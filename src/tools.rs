@@ -0,0 +1,355 @@
+//! Registry of the external tools vid2txt runs. Each one is resolved once, from an
+//! explicit path (`--ffmpeg`, `--yt-dlp`, `--whisper-cli`, their env vars or config
+//! keys) or by searching PATH for its known names, and every stage takes its binary
+//! from here.
+
+use crate::cli::ToolArgs;
+use crate::error::Vid2txtError;
+use anyhow::Result;
+use std::env::consts::EXE_SUFFIX;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command as PCommand;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Ffmpeg,
+    Ffprobe,
+    YtDlp,
+    WhisperCli,
+    WhisperServer,
+}
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Ffmpeg => "ffmpeg",
+            Tool::Ffprobe => "ffprobe",
+            Tool::YtDlp => "yt-dlp",
+            Tool::WhisperCli => "whisper-cli",
+            Tool::WhisperServer => "whisper-server",
+        }
+    }
+
+    /// Names searched in PATH, preferred first. Distro packages ship whisper.cpp as
+    /// `whisper-cpp`/`whisper-cpp-server`. Builds before 1.7.4 called the CLI `main`,
+    /// which is only looked for in a whisper.cpp directory given as `--whisper-cli`.
+    pub fn names(self) -> &'static [&'static str] {
+        match self {
+            Tool::Ffmpeg => &["ffmpeg"],
+            Tool::Ffprobe => &["ffprobe"],
+            Tool::YtDlp => &["yt-dlp"],
+            Tool::WhisperCli => &["whisper-cli", "whisper-cpp"],
+            Tool::WhisperServer => &["whisper-server", "whisper-cpp-server"],
+        }
+    }
+
+    /// How to point vid2txt at the binary explicitly.
+    fn override_hint(self) -> Option<&'static str> {
+        match self {
+            Tool::Ffmpeg => Some("--ffmpeg or VID2TXT_FFMPEG"),
+            Tool::YtDlp => Some("--yt-dlp or VID2TXT_YT_DLP"),
+            Tool::WhisperCli => Some("--whisper-cli or VID2TXT_WHISPER_CLI"),
+            Tool::Ffprobe | Tool::WhisperServer => None,
+        }
+    }
+}
+
+/// Resolved tool paths; a tool that wasn't found is simply absent.
+#[derive(Debug, Clone, Default)]
+pub struct Tools {
+    found: Vec<(Tool, PathBuf)>,
+}
+
+impl Tools {
+    /// Explicit paths win; ffprobe and whisper-server are looked for next to an
+    /// explicit ffmpeg and the resolved whisper-cli before PATH is searched.
+    pub fn resolve(args: &ToolArgs) -> Result<Self> {
+        let ffmpeg = explicit(args.ffmpeg.as_deref(), "--ffmpeg")?;
+        let yt_dlp = explicit(args.yt_dlp.as_deref(), "--yt-dlp")?;
+        let whisper_cli = match args.whisper_cli.as_deref() {
+            Some(dir) if dir.is_dir() => Some(in_build_dir(dir).ok_or_else(|| {
+                Vid2txtError::MissingTool(format!(
+                    "--whisper-cli {}: no whisper-cli (or old main) in this whisper.cpp directory",
+                    dir.display()
+                ))
+            })?),
+            path => explicit(path, "--whisper-cli")?,
+        };
+
+        let ffmpeg = ffmpeg.or_else(|| search(Tool::Ffmpeg));
+        let ffprobe = args
+            .ffmpeg
+            .as_ref()
+            .and(ffmpeg.as_deref())
+            .and_then(|f| sibling(f, "ffprobe"))
+            .or_else(|| search(Tool::Ffprobe));
+        let whisper_cli = whisper_cli.or_else(|| search(Tool::WhisperCli));
+        let whisper_server = whisper_cli
+            .as_deref()
+            .and_then(|w| sibling(w, "whisper-server"))
+            .or_else(|| search(Tool::WhisperServer));
+
+        let found = [
+            (Tool::Ffmpeg, ffmpeg),
+            (Tool::Ffprobe, ffprobe),
+            (Tool::YtDlp, yt_dlp.or_else(|| search(Tool::YtDlp))),
+            (Tool::WhisperCli, whisper_cli),
+            (Tool::WhisperServer, whisper_server),
+        ]
+        .into_iter()
+        .filter_map(|(tool, path)| path.map(|p| (tool, p)))
+        .collect();
+        Ok(Self { found })
+    }
+
+    pub fn get(&self, tool: Tool) -> Option<&Path> {
+        self.found
+            .iter()
+            .find(|(t, _)| *t == tool)
+            .map(|(_, p)| p.as_path())
+    }
+
+    pub fn require(&self, tool: Tool) -> Result<&Path> {
+        self.get(tool).ok_or_else(|| {
+            let set = tool
+                .override_hint()
+                .map(|h| format!("set {h}, or "))
+                .unwrap_or_default();
+            Vid2txtError::MissingTool(format!(
+                "Required tool '{}' not found in PATH (looked for {}); {set}run `vid2txt doctor` to check your setup",
+                tool.name(),
+                tool.names().join(", ")
+            ))
+            .into()
+        })
+    }
+
+    /// A `Command` for the resolved binary.
+    pub fn command(&self, tool: Tool) -> Result<PCommand> {
+        Ok(PCommand::new(self.require(tool)?))
+    }
+}
+
+/// A user-given path: anything with a directory part must exist, a bare name is
+/// looked up in PATH.
+fn explicit(path: Option<&Path>, flag: &str) -> Result<Option<PathBuf>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    if path.components().count() > 1 {
+        if path.is_file() {
+            return Ok(Some(path.to_path_buf()));
+        }
+        return Err(
+            Vid2txtError::MissingTool(format!("{flag} {}: no such file", path.display())).into(),
+        );
+    }
+    which::which(path).map(Some).map_err(|_| {
+        Vid2txtError::MissingTool(format!("{flag} {}: not found in PATH", path.display())).into()
+    })
+}
+
+fn search(tool: Tool) -> Option<PathBuf> {
+    search_in(tool, std::env::var_os("PATH")?)
+}
+
+/// `tool` in a PATH-style list of directories.
+fn search_in(tool: Tool, path: impl AsRef<OsStr>) -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    tool.names()
+        .iter()
+        .find_map(|name| which::which_in(name, Some(path.as_ref()), &cwd).ok())
+}
+
+/// whisper-cli in a whisper.cpp checkout or build directory, falling back to an old
+/// build's `main`.
+fn in_build_dir(dir: &Path) -> Option<PathBuf> {
+    let dirs = [
+        dir.to_path_buf(),
+        dir.join("build").join("bin"),
+        dir.join("bin"),
+    ];
+    ["whisper-cli", "main"].into_iter().find_map(|name| {
+        dirs.iter()
+            .map(|d| d.join(format!("{name}{EXE_SUFFIX}")))
+            .find(|p| p.is_file() && (name != "main" || is_whisper_main(p)))
+    })
+}
+
+/// `main` is too generic a name to trust: require whisper.cpp's usage text, and skip
+/// the stub newer builds leave behind that only prints a deprecation warning.
+fn is_whisper_main(path: &Path) -> bool {
+    let Ok(out) = PCommand::new(path).arg("--help").output() else {
+        return false;
+    };
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&out.stdout),
+        String::from_utf8_lossy(&out.stderr)
+    );
+    text.contains("--output-txt") && !text.contains("deprecated")
+}
+
+fn sibling(bin: &Path, name: &str) -> Option<PathBuf> {
+    let p = bin.parent()?.join(format!("{name}{EXE_SUFFIX}"));
+    p.is_file().then_some(p)
+}
+
+/// `cmd` as a copy-pasteable shell line (for `--dry-run`).
+pub fn command_line(cmd: &PCommand) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|a| shell_quote(&a.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// An executable shell script `name` in `dir`.
+    #[cfg(unix)]
+    fn script(dir: &Path, name: &str, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(name);
+        fs::create_dir_all(dir).unwrap();
+        fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn missing_tool(e: anyhow::Error) -> String {
+        match e.downcast_ref::<Vid2txtError>() {
+            Some(Vid2txtError::MissingTool(m)) => m.clone(),
+            _ => panic!("not a missing tool: {e}"),
+        }
+    }
+
+    #[test]
+    fn explicit_paths_must_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("ffmpeg");
+        fs::write(&file, "").unwrap();
+        assert_eq!(explicit(None, "--ffmpeg").unwrap(), None);
+        assert_eq!(explicit(Some(&file), "--ffmpeg").unwrap(), Some(file));
+
+        let missing = dir.path().join("nope");
+        let err = missing_tool(explicit(Some(&missing), "--ffmpeg").unwrap_err());
+        assert_eq!(err, format!("--ffmpeg {}: no such file", missing.display()));
+        // a directory is not a binary
+        assert!(explicit(Some(dir.path()), "--ffmpeg").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn explicit_bare_names_are_looked_up_in_path() {
+        assert!(
+            explicit(Some(Path::new("sh")), "--yt-dlp")
+                .unwrap()
+                .unwrap()
+                .is_absolute()
+        );
+        let err = explicit(Some(Path::new("vid2txt-no-such-tool")), "--yt-dlp").unwrap_err();
+        assert_eq!(
+            missing_tool(err),
+            "--yt-dlp vid2txt-no-such-tool: not found in PATH"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn searches_alternate_names() {
+        let distro = tempfile::tempdir().unwrap();
+        let cli = script(distro.path(), "whisper-cpp", "");
+        let server = script(distro.path(), "whisper-cpp-server", "");
+        assert_eq!(search_in(Tool::WhisperCli, distro.path()), Some(cli));
+        assert_eq!(search_in(Tool::WhisperServer, distro.path()), Some(server));
+        assert_eq!(search_in(Tool::Ffmpeg, distro.path()), None);
+
+        // the upstream name wins, wherever it is in PATH
+        let upstream = tempfile::tempdir().unwrap();
+        let preferred = script(upstream.path(), "whisper-cli", "");
+        let path = std::env::join_paths([distro.path(), upstream.path()]).unwrap();
+        assert_eq!(search_in(Tool::WhisperCli, &path), Some(preferred));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn finds_whisper_cli_in_build_dirs() {
+        let checkout = tempfile::tempdir().unwrap();
+        assert_eq!(in_build_dir(checkout.path()), None);
+        let built = script(
+            &checkout.path().join("build").join("bin"),
+            "whisper-cli",
+            "",
+        );
+        assert_eq!(in_build_dir(checkout.path()), Some(built.clone()));
+        assert_eq!(
+            in_build_dir(&checkout.path().join("build").join("bin")),
+            Some(built)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn old_main_needs_whisper_usage() {
+        let usage =
+            "echo '  -otxt,     --output-txt        [false  ] output result in a text file' >&2";
+        let deprecated = "echo \"WARNING: The binary 'main' is deprecated. Please use 'whisper-cli' instead.\" >&2";
+
+        let old = tempfile::tempdir().unwrap();
+        let main = script(old.path(), "main", usage);
+        assert!(is_whisper_main(&main));
+        assert_eq!(in_build_dir(old.path()), Some(main));
+
+        let stub = tempfile::tempdir().unwrap();
+        let main = script(&stub.path().join("bin"), "main", deprecated);
+        assert!(!is_whisper_main(&main));
+        assert_eq!(in_build_dir(stub.path()), None);
+
+        let other = tempfile::tempdir().unwrap();
+        let main = script(other.path(), "main", "echo 'usage: main [file]'");
+        assert!(!is_whisper_main(&main));
+        assert!(!is_whisper_main(&other.path().join("missing")));
+        // whisper-cli is preferred over a working main
+        let newer = tempfile::tempdir().unwrap();
+        script(newer.path(), "main", usage);
+        let cli = script(newer.path(), "whisper-cli", "");
+        assert_eq!(in_build_dir(newer.path()), Some(cli));
+    }
+
+    #[test]
+    fn quotes_shell_arguments() {
+        for (arg, quoted) in [
+            ("-i", "-i"),
+            ("/tmp/a_b-c.wav", "/tmp/a_b-c.wav"),
+            ("https://x.org/v?id=1", "'https://x.org/v?id=1'"),
+            ("my talk.mp4", "'my talk.mp4'"),
+            ("it's.mp4", r"'it'\''s.mp4'"),
+            ("", "''"),
+        ] {
+            assert_eq!(shell_quote(arg), quoted, "{arg}");
+        }
+        let mut cmd = PCommand::new("/usr/bin/ffmpeg");
+        cmd.args(["-i", "my talk.mp4", "-ar", "16000", "out.wav"]);
+        assert_eq!(
+            command_line(&cmd),
+            "/usr/bin/ffmpeg -i 'my talk.mp4' -ar 16000 out.wav"
+        );
+    }
+}
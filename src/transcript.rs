@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Backend-independent transcription result.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Transcript {
+    /// Language reported by the backend (e.g. the auto-detected one)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Confidence of the language detection pass, when one ran
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_probability: Option<f32>,
+    /// Mean token probability over the whole transcript, when the backend reports one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    /// Models tried before this result, when a fallback kicked in
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<Attempt>,
+    pub segments: Vec<Segment>,
+}
+
+/// One transcription attempt in a model fallback chain.
+#[derive(Debug, Clone, Serialize)]
+pub struct Attempt {
+    /// Model file name
+    pub model: String,
+    /// "ok", "out of memory", "low confidence 41%", ...
+    pub outcome: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Segment {
+    /// Seconds from the start of the audio
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    /// Probability that the window held no speech (verbose_json and in-process only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_speech_prob: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<Word>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Word {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probability: Option<f32>,
+}
+
+impl Transcript {
+    pub fn is_empty(&self) -> bool {
+        self.segments.iter().all(|s| s.text.trim().is_empty())
+    }
+
+    /// One line per segment, prefixed with the speaker when diarized.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for seg in &self.segments {
+            let text = seg.text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            if let Some(sp) = &seg.speaker {
+                out.push_str(&format!("[{sp}] "));
+            }
+            out.push_str(text);
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn write_txt(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_text())
+            .with_context(|| format!("Failed to write transcript {}", path.display()))
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write transcript {}", path.display()))
+    }
+}